@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use twitter_image_downloader::filter::MediaKinds;
+use twitter_image_downloader::{manifest, sidecar, walk, Media};
+
+/// Maximum number of tweets, which can be looked up with a single API request.
+const LOOKUP_BATCH_SIZE: usize = 100;
+
+/// Tweet id contained in the given relative path, like `1234567890123456789_1.jpg` or
+/// `1234567890123456789/photo.jpg`.
+///
+/// Tweet ids are sequences of 15 to 20 digits; the last one in the path is used.
+fn tweet_id(relative_path: &str) -> Option<u64> {
+    relative_path
+        .split(|character: char| !character.is_ascii_digit())
+        .filter(|digits| (15..=20).contains(&digits.len()))
+        .filter_map(|digits| digits.parse().ok())
+        .next_back()
+}
+
+/// Whether the given file name looks like the name twitter assigns to uploaded images (the
+/// media key, eg. `EaBcD1234xyz_AB.jpg`).
+fn is_media_key(file_name: &str) -> bool {
+    let stem = file_name.split('.').next().unwrap_or_default();
+    stem.len() == 15
+        && stem.chars().all(|character| {
+            character.is_ascii_alphanumeric() || character == '-' || character == '_'
+        })
+}
+
+/// Look up the media of the given tweets.
+async fn lookup(token: &egg_mode::Token, tweet_ids: Vec<u64>) -> HashMap<u64, Vec<Media>> {
+    let kinds = MediaKinds {
+        photos: true,
+        gifs: true,
+        videos: true,
+    };
+    let mut media = HashMap::new();
+    for batch in tweet_ids.chunks(LOOKUP_BATCH_SIZE) {
+        let tweets = match egg_mode::tweet::lookup(batch.iter().copied(), token).await {
+            Ok(tweets) => tweets.response,
+            Err(err) => {
+                eprintln!("Could not look up tweets: {}", err);
+                continue;
+            }
+        };
+        for tweet in tweets {
+            let author = tweet
+                .user
+                .as_ref()
+                .map(|user| user.screen_name.clone())
+                .unwrap_or_default();
+            media.insert(
+                tweet.id,
+                twitter_image_downloader::tweet_media(&tweet, &author, &kinds),
+            );
+        }
+    }
+    media
+}
+
+/// Index a directory of previously downloaded images (from older versions or other tools).
+///
+/// Every image is recorded in the archive database, so later runs with `--archive-db` do not
+/// download it again. Images, whose tweet can be determined from the file name and looked up
+/// using the given API token, also receive a metadata sidecar.
+pub async fn adopt(output_directory: &Path, token: Option<&egg_mode::Token>) {
+    let files = walk::files(output_directory, &walk::MEDIA_EXTENSIONS);
+    let manifest = manifest::Manifest::open(output_directory);
+
+    let files: Vec<(PathBuf, String)> = files
+        .into_iter()
+        .map(|path| {
+            let relative_path = walk::relative_path(output_directory, &path);
+            (path, relative_path)
+        })
+        .filter(|(_, relative_path)| manifest.url_by_path(relative_path).is_none())
+        .collect();
+    if files.is_empty() {
+        println!("No new images found in {:?}", output_directory);
+        return;
+    }
+
+    let mut tweet_ids: Vec<u64> = files
+        .iter()
+        .filter_map(|(_, relative_path)| tweet_id(relative_path))
+        .collect();
+    tweet_ids.sort_unstable();
+    tweet_ids.dedup();
+    let tweets = match token {
+        Some(token) if !tweet_ids.is_empty() => {
+            println!("Looking up {} tweets...", tweet_ids.len());
+            lookup(token, tweet_ids).await
+        }
+        _ => HashMap::new(),
+    };
+
+    let (mut matched, mut unmatched) = (0, 0);
+    for (path, relative_path) in files {
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(err) => {
+                eprintln!("Could not read {:?}: {}", path, err);
+                continue;
+            }
+        };
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let tweet_id = tweet_id(&relative_path);
+        let media = tweet_id
+            .and_then(|tweet_id| tweets.get(&tweet_id))
+            .and_then(|media| {
+                media
+                    .iter()
+                    .find(|media| media.url.ends_with(&format!("/{}", file_name)))
+                    .or(match media.as_slice() {
+                        [only] => Some(only),
+                        _ => None,
+                    })
+            });
+
+        let url = match media {
+            Some(media) => {
+                let sidecar_path = sidecar::path(&path);
+                if !sidecar_path.exists() {
+                    if let Err(err) = sidecar::write(&path, media).await {
+                        eprintln!("{}", err);
+                    }
+                }
+                matched += 1;
+                media.url.clone()
+            }
+            None if is_media_key(&file_name) => {
+                unmatched += 1;
+                format!("https://pbs.twimg.com/media/{}", file_name)
+            }
+            None => {
+                unmatched += 1;
+                String::new()
+            }
+        };
+        let tweet_id = media.map(|media| media.tweet_id).or(tweet_id).unwrap_or(0);
+        manifest.record(tweet_id, &url, &relative_path, &manifest::hash(&data));
+    }
+
+    println!(
+        "Adopted {} images: {} matched to their tweets, {} indexed by content only",
+        matched + unmatched,
+        matched,
+        unmatched
+    );
+}
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+
+enum Format {
+    Zip(zip::ZipWriter<File>),
+    Tar(tar::Builder<Box<dyn Write + Send>>),
+}
+
+/// Single archive file receiving all downloads instead of individual files.
+///
+/// The format is chosen by the extension: `.zip`, `.tar` or `.tar.zst`. Media files are already
+/// compressed, therefore zip entries are stored without further compression.
+///
+/// The archive is written to a `.part` file, which is only moved into place once it is complete.
+pub struct Archive {
+    path: String,
+    part_path: String,
+    format: Mutex<Format>,
+}
+
+impl Archive {
+    /// Check whether the format of the given archive filename is supported.
+    pub fn validate(path: &str) -> Result<(), String> {
+        let supported = [".zip", ".tar", ".tar.zst", ".tzst"];
+        if supported.iter().any(|extension| path.ends_with(extension)) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Unknown archive format of {} (use .zip, .tar or .tar.zst)",
+                path
+            ))
+        }
+    }
+
+    pub fn create(path: &str) -> Result<Archive, String> {
+        Archive::validate(path)?;
+        let failed = |err: std::io::Error| format!("Could not create archive {}: {}", path, err);
+        let part_path = format!("{}.part", path);
+        let file = File::create(&part_path).map_err(failed)?;
+        let format = if path.ends_with(".zip") {
+            Format::Zip(zip::ZipWriter::new(file))
+        } else if path.ends_with(".tar") {
+            Format::Tar(tar::Builder::new(Box::new(file)))
+        } else {
+            let encoder = zstd::Encoder::new(file, 0).map_err(failed)?;
+            Format::Tar(tar::Builder::new(Box::new(encoder.auto_finish())))
+        };
+        Ok(Archive {
+            path: path.to_string(),
+            part_path,
+            format: Mutex::new(format),
+        })
+    }
+
+    /// Add a file with the given relative path to the archive.
+    pub fn add(&self, name: &str, data: &[u8], modified: Option<DateTime<Utc>>) {
+        let modified = modified.unwrap_or_else(Utc::now);
+        let failed = |err: &dyn std::fmt::Display| -> ! {
+            panic!("Could not add {} to archive {}: {}", name, self.path, err)
+        };
+        match &mut *self.format.lock().unwrap() {
+            Format::Zip(writer) => {
+                let mut options = zip::write::FileOptions::default()
+                    .compression_method(zip::CompressionMethod::Stored)
+                    .large_file(data.len() as u64 >= u32::MAX as u64);
+                if let Ok(time) = zip::DateTime::from_date_and_time(
+                    modified.year() as u16,
+                    modified.month() as u8,
+                    modified.day() as u8,
+                    modified.hour() as u8,
+                    modified.minute() as u8,
+                    modified.second() as u8,
+                ) {
+                    options = options.last_modified_time(time);
+                }
+                writer
+                    .start_file(name, options)
+                    .unwrap_or_else(|err| failed(&err));
+                writer.write_all(data).unwrap_or_else(|err| failed(&err));
+            }
+            Format::Tar(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(modified.timestamp().max(0) as u64);
+                header.set_cksum();
+                builder
+                    .append_data(&mut header, name, data)
+                    .unwrap_or_else(|err| failed(&err));
+            }
+        }
+    }
+
+    /// Write the archive index, flush all buffered data and move the archive into place.
+    pub fn finish(self) {
+        let path = self.path;
+        let part_path = self.part_path;
+        match self.format.into_inner().unwrap() {
+            Format::Zip(mut writer) => {
+                writer
+                    .finish()
+                    .unwrap_or_else(|err| panic!("Could not finish archive {}: {}", path, err));
+            }
+            Format::Tar(builder) => {
+                // Dropping the writer finishes the compression.
+                builder
+                    .into_inner()
+                    .unwrap_or_else(|err| panic!("Could not finish archive {}: {}", path, err));
+            }
+        }
+        File::open(&part_path)
+            .and_then(|file| file.sync_all())
+            .and_then(|_| std::fs::rename(&part_path, &path))
+            .unwrap_or_else(|err| panic!("Could not finish archive {}: {}", path, err));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::progress;
+use egg_mode::service::TweetMethod;
+use egg_mode::user::TwitterUser;
+use std::io::{IsTerminal, Write};
+
+const VERIFY_CREDENTIALS_URL: &str = "https://api.twitter.com/1.1/account/verify_credentials.json";
+const RATE_LIMIT_STATUS_URL: &str =
+    "https://api.twitter.com/1.1/application/rate_limit_status.json";
+
+/// Error code returned for v1.1 endpoints, which are not part of the API tier of the credentials.
+const LIMITED_ACCESS: i32 = 453;
+/// Error code returned if the credentials are not allowed to access a resource.
+const NO_ACCESS: i32 = 220;
+
+/// Verify the given credentials against the twitter API and report the account they belong to,
+/// the granted access level and the remaining rate limits.
+///
+/// Returns the reason as error if the credentials are not usable.
+pub async fn verify(token: &egg_mode::Token) -> Result<(), String> {
+    let request = egg_mode::raw::request_get(VERIFY_CREDENTIALS_URL, token, None);
+    let (headers, body) = match egg_mode::raw::response_raw_bytes(request).await {
+        Ok(response) => response,
+        Err(err) => {
+            return Err(format!(
+                "The given credentials could not be verified: {}",
+                err
+            ))
+        }
+    };
+    let user: TwitterUser = match serde_json::from_slice(&body) {
+        Ok(user) => user,
+        Err(err) => {
+            return Err(format!(
+                "Unexpected response while verifying credentials: {}",
+                err
+            ))
+        }
+    };
+
+    println!(
+        "Credentials belong to @{} ({}, id {})",
+        user.screen_name, user.name, user.id
+    );
+
+    let access_level = headers
+        .get("x-access-level")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("unknown");
+    println!("Access level: {}", access_level);
+
+    match egg_mode::service::rate_limit_status(token).await {
+        Ok(status) => match status.tweet.get(&TweetMethod::UserTimeline) {
+            Some(limit) => println!(
+                "User timeline rate limit: {} / {} requests remaining (resets at {})",
+                limit.rate_limit_status.remaining,
+                limit.rate_limit_status.limit,
+                limit.rate_limit_status.reset
+            ),
+            None => println!("User timeline rate limit: unknown"),
+        },
+        Err(err) => println!("Rate limits could not be retrieved: {}", err),
+    }
+
+    if !access_level.starts_with("read") {
+        return Err(
+            "The credentials do not grant read access, which is needed to fetch timelines."
+                .to_string(),
+        );
+    }
+
+    if let Err(reason) = timeline_access(token).await {
+        return Err(format!(
+            "The credentials can not be used to fetch timelines: {}",
+            reason
+        ));
+    }
+
+    println!("Credentials are valid and grant all needed permissions.");
+    Ok(())
+}
+
+/// Determine whether the given credentials are able to read user timelines through the v1.1 API.
+///
+/// Only definite answers about the capabilities are reported as errors. Network failures, rate
+/// limits and expired credentials (which may be renewed interactively later on) are not.
+pub async fn timeline_access(token: &egg_mode::Token) -> Result<(), String> {
+    let params = egg_mode::raw::ParamList::new().add_param("resources", "statuses");
+    let request = egg_mode::raw::request_get(RATE_LIMIT_STATUS_URL, token, Some(&params));
+    match egg_mode::raw::response_raw_bytes(request).await {
+        Err(egg_mode::error::Error::TwitterError(_, errors)) => {
+            if errors
+                .errors
+                .iter()
+                .any(|error| error.code == LIMITED_ACCESS)
+            {
+                Err(
+                    "the API access tier of the credentials does not include the v1.1 timelines"
+                        .to_string(),
+                )
+            } else if errors.errors.iter().any(|error| error.code == NO_ACCESS) {
+                Err("the credentials are not allowed to read timelines".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        Err(egg_mode::error::Error::BadStatus(status)) if status.as_u16() == 403 => {
+            Err("the credentials are not allowed to read timelines".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Check all given credentials for their capabilities and only keep the ones able to read user
+/// timelines.
+///
+/// Returns an error if none of them is usable.
+pub async fn usable_tokens(tokens: Vec<egg_mode::Token>) -> Result<Vec<egg_mode::Token>, String> {
+    let mut usable = vec![];
+    for (index, token) in tokens.into_iter().enumerate() {
+        match timeline_access(&token).await {
+            Ok(()) => usable.push(token),
+            Err(reason) => eprintln!("Skipping credential set {}: {}", index + 1, reason),
+        }
+    }
+    if usable.is_empty() {
+        return Err("None of the given credentials is able to read user timelines. Use --via-nitter or --cookies-from-browser to retrieve the images without API access.".to_string());
+    }
+    Ok(usable)
+}
+
+/// Whether the given API error indicates, that the used credentials are no longer valid.
+pub fn is_expired(err: &egg_mode::error::Error) -> bool {
+    match err {
+        egg_mode::error::Error::TwitterError(_, errors) => errors
+            .errors
+            .iter()
+            .any(|error| error.code == 89 || error.code == 32),
+        egg_mode::error::Error::BadStatus(status) => status.as_u16() == 401,
+        _ => false,
+    }
+}
+
+/// Whether the given API error indicates, that the rate limit of the used credentials is exhausted.
+pub fn is_rate_limited(err: &egg_mode::error::Error) -> bool {
+    match err {
+        egg_mode::error::Error::RateLimit(_) => true,
+        egg_mode::error::Error::TwitterError(_, errors) => {
+            errors.errors.iter().any(|error| error.code == 88)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the given API error is likely to go away, if the request is simply sent again.
+pub fn is_transient(err: &egg_mode::error::Error) -> bool {
+    match err {
+        egg_mode::error::Error::NetError(_) | egg_mode::error::Error::IOError(_) => true,
+        egg_mode::error::Error::BadStatus(status) => status.is_server_error(),
+        // Over capacity and internal error.
+        egg_mode::error::Error::TwitterError(_, errors) => errors
+            .errors
+            .iter()
+            .any(|error| error.code == 130 || error.code == 131),
+        _ => false,
+    }
+}
+
+/// Unix time, at which the exhausted rate limit indicated by the given API error resets, if known.
+pub fn rate_limit_reset(err: &egg_mode::error::Error) -> Option<i64> {
+    match err {
+        egg_mode::error::Error::RateLimit(reset) => Some(*reset as i64),
+        egg_mode::error::Error::TwitterError(headers, _) => headers
+            .get("x-rate-limit-reset")?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok(),
+        _ => None,
+    }
+}
+
+/// Ask the user for a single line of input on stderr, returning `None` for an empty answer.
+pub fn prompt(message: &str) -> Option<String> {
+    read_answer(message).ok().flatten()
+}
+
+/// Ask the user for a single line of input on stderr like `prompt`, but tell the end of the input
+/// (eg. a closed stdin) apart from an empty answer by returning an error for it.
+pub fn read_answer(message: &str) -> std::io::Result<Option<String>> {
+    eprint!("{}", message);
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line)? == 0 {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    let line = line.trim();
+    if line.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(line.to_string()))
+    }
+}
+
+/// Try to obtain a fresh access token after the given one expired.
+///
+/// New access credentials can only be entered interactively, therefore `None` is returned if
+/// stdin is not attached to a terminal, or the user chose not to provide new credentials. The
+/// progress display of the given spinner is suspended meanwhile, while the downloads continue.
+pub async fn reauthenticate(
+    token: &egg_mode::Token,
+    spinner: &progress::Spinner,
+) -> Option<egg_mode::Token> {
+    let consumer = match token {
+        egg_mode::Token::Access { consumer, .. } => consumer.clone(),
+        egg_mode::Token::Bearer(_) => return None,
+    };
+    if !std::io::stdin().is_terminal() {
+        return None;
+    }
+
+    let _suspended = spinner.suspend();
+    let span = tracing::Span::current();
+    let (access_token, access_token_secret) = tokio::task::spawn_blocking(move || {
+        let _entered = span.enter();
+        eprintln!(
+            "The access token has expired. Enter new credentials to resume (empty to abort)."
+        );
+        let access_token = prompt("Access Token: ")?;
+        let access_token_secret = prompt("Access Token Secret: ")?;
+        Some((access_token, access_token_secret))
+    })
+    .await
+    .ok()??;
+
+    Some(egg_mode::Token::Access {
+        consumer,
+        access: egg_mode::KeyPair::new(access_token, access_token_secret),
+    })
+}
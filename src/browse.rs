@@ -0,0 +1,353 @@
+use crate::exit_code;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+use std::path::{Path, PathBuf};
+use twitter_image_downloader::{sidecar, walk};
+
+/// Name of the group containing every archived image.
+const ALL: &str = "All";
+
+/// An archived image together with the metadata of its tweet.
+struct Entry {
+    path: PathBuf,
+    /// Path relative to the output directory, as shown in the list.
+    name: String,
+    author: String,
+    month: String,
+    text: String,
+    tweet_url: String,
+    tags: Vec<String>,
+}
+
+impl Entry {
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        [&self.name, &self.author, &self.text]
+            .iter()
+            .any(|value| value.to_lowercase().contains(&query))
+            || self
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(&query))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Grouping {
+    User,
+    Month,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    Groups,
+    Entries,
+}
+
+struct Browser {
+    entries: Vec<Entry>,
+    grouping: Grouping,
+    groups: Vec<String>,
+    group_state: ListState,
+    entry_state: ListState,
+    focus: Focus,
+    query: String,
+    searching: bool,
+    status: Option<String>,
+}
+
+/// Read all images with metadata sidecars below the given directory.
+fn load(output_directory: &Path) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = sidecar::find(output_directory)
+        .into_iter()
+        .filter_map(|sidecar_path| {
+            let metadata = sidecar::read(&sidecar_path)?;
+            let path = sidecar_path.with_extension("");
+            if !path.exists() {
+                return None;
+            }
+            let name = walk::relative_path(output_directory, &path);
+            Some(Entry {
+                name,
+                month: metadata
+                    .created_at
+                    .map(|created_at| created_at.format("%Y-%m").to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                tweet_url: format!(
+                    "https://twitter.com/{}/status/{}",
+                    metadata.original_author, metadata.tweet_id
+                ),
+                author: metadata.original_author,
+                text: metadata.text.unwrap_or_default(),
+                tags: metadata.tags,
+                path,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| b.month.cmp(&a.month).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+/// Open the given file in the default viewer of the system.
+fn open(path: &Path) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command
+        .arg(path)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+impl Browser {
+    fn new(entries: Vec<Entry>) -> Browser {
+        let mut browser = Browser {
+            entries,
+            grouping: Grouping::User,
+            groups: vec![],
+            group_state: ListState::default(),
+            entry_state: ListState::default(),
+            focus: Focus::Groups,
+            query: String::new(),
+            searching: false,
+            status: None,
+        };
+        browser.update_groups();
+        browser
+    }
+
+    fn group_of<'a>(&self, entry: &'a Entry) -> &'a str {
+        match self.grouping {
+            Grouping::User => &entry.author,
+            Grouping::Month => &entry.month,
+        }
+    }
+
+    fn update_groups(&mut self) {
+        let mut groups: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| self.group_of(entry).to_string())
+            .collect();
+        groups.sort();
+        groups.dedup();
+        if self.grouping == Grouping::Month {
+            groups.reverse();
+        }
+        groups.insert(0, ALL.to_string());
+        self.groups = groups;
+        self.group_state.select(Some(0));
+        self.entry_state.select(Some(0));
+    }
+
+    /// Entries of the selected group, which match the search query.
+    fn visible(&self) -> Vec<&Entry> {
+        let group = self
+            .group_state
+            .selected()
+            .and_then(|index| self.groups.get(index))
+            .map(String::as_str)
+            .unwrap_or(ALL);
+        self.entries
+            .iter()
+            .filter(|entry| group == ALL || self.group_of(entry) == group)
+            .filter(|entry| self.query.is_empty() || entry.matches(&self.query))
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        let index = self.entry_state.selected()?;
+        self.visible().get(index).copied()
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        let (state, length) = match self.focus {
+            Focus::Groups => (&mut self.group_state, self.groups.len()),
+            Focus::Entries => {
+                let length = self.visible().len();
+                (&mut self.entry_state, length)
+            }
+        };
+        if length == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        state.select(Some(
+            (current + offset).clamp(0, length as isize - 1) as usize
+        ));
+        if self.focus == Focus::Groups {
+            self.entry_state.select(Some(0));
+        }
+    }
+
+    /// Handle a key press, returning `false` once the browser should be closed.
+    fn handle(&mut self, key: KeyCode) -> bool {
+        self.status = None;
+        if self.searching {
+            match key {
+                KeyCode::Enter | KeyCode::Esc => self.searching = false,
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Char(character) => self.query.push(character),
+                _ => {}
+            }
+            self.entry_state.select(Some(0));
+            return true;
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::PageDown => self.move_selection(10),
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Groups,
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => self.focus = Focus::Entries,
+            KeyCode::Char('/') => {
+                self.searching = true;
+                self.query.clear();
+            }
+            KeyCode::Char('g') => {
+                self.grouping = match self.grouping {
+                    Grouping::User => Grouping::Month,
+                    Grouping::Month => Grouping::User,
+                };
+                self.update_groups();
+            }
+            KeyCode::Enter if self.focus == Focus::Groups => self.focus = Focus::Entries,
+            KeyCode::Enter | KeyCode::Char('o') => {
+                if let Some(entry) = self.selected() {
+                    let status = match open(&entry.path) {
+                        Ok(()) => format!("Opened {}", entry.name),
+                        Err(err) => format!("Could not open {}: {}", entry.name, err),
+                    };
+                    self.status = Some(status);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, details, help] = Layout::vertical([
+            Constraint::Min(5),
+            Constraint::Length(6),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [groups, entries] =
+            Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .areas(main);
+
+        let highlight = |focused: bool| {
+            if focused {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default().add_modifier(Modifier::BOLD)
+            }
+        };
+
+        let title = match self.grouping {
+            Grouping::User => "Users",
+            Grouping::Month => "Months",
+        };
+        let group_list = List::new(self.groups.clone())
+            .block(Block::bordered().title(title))
+            .highlight_style(highlight(self.focus == Focus::Groups));
+        frame.render_stateful_widget(group_list, groups, &mut self.group_state);
+
+        let visible = self.visible();
+        let names: Vec<String> = visible.iter().map(|entry| entry.name.clone()).collect();
+        let details_text = match self.entry_state.selected().and_then(|i| visible.get(i)) {
+            Some(entry) => {
+                let mut lines = vec![
+                    Line::from(format!("{} ({})", entry.tweet_url, entry.month)),
+                    Line::from(entry.text.clone()),
+                ];
+                if !entry.tags.is_empty() {
+                    lines.push(Line::from(format!("Tags: {}", entry.tags.join(", "))));
+                }
+                lines
+            }
+            None => vec![Line::from("No images")],
+        };
+        let entry_title = if self.query.is_empty() {
+            format!("Images ({})", names.len())
+        } else {
+            format!("Images matching '{}' ({})", self.query, names.len())
+        };
+        let entry_list = List::new(names)
+            .block(Block::bordered().title(entry_title))
+            .highlight_style(highlight(self.focus == Focus::Entries));
+        frame.render_stateful_widget(entry_list, entries, &mut self.entry_state);
+
+        frame.render_widget(
+            Paragraph::new(details_text)
+                .block(Block::bordered())
+                .wrap(Wrap { trim: true }),
+            details,
+        );
+
+        let help_text = if self.searching {
+            format!("Search: {}_  (Enter/Esc to finish)", self.query)
+        } else if let Some(status) = &self.status {
+            status.clone()
+        } else {
+            "↑/↓ move  ←/→ switch list  / search  g group by user/month  Enter open  q quit"
+                .to_string()
+        };
+        frame.render_widget(Paragraph::new(help_text), help);
+    }
+}
+
+/// Interactively browse the images archived in the given directory.
+///
+/// The images have to be downloaded with `--write-metadata`.
+pub fn browse(output_directory: &Path) {
+    let entries = load(output_directory);
+    if entries.is_empty() {
+        eprintln!(
+            "No images with metadata found in {:?}. Download them using --write-metadata first.",
+            output_directory
+        );
+        std::process::exit(exit_code::USAGE_ERROR);
+    }
+
+    let mut browser = Browser::new(entries);
+    let mut terminal = ratatui::init();
+    let result = loop {
+        if let Err(err) = terminal.draw(|frame| browser.draw(frame)) {
+            break Err(err);
+        }
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if !browser.handle(key.code) {
+                    break Ok(());
+                }
+            }
+            Ok(_) => {}
+            Err(err) => break Err(err),
+        }
+    };
+    ratatui::restore();
+
+    if let Err(err) = result {
+        eprintln!("Could not draw the archive browser: {}", err);
+        std::process::exit(exit_code::IO_ERROR);
+    }
+}
@@ -0,0 +1,236 @@
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// Domains, whose cookies are needed to authenticate against twitter.
+const COOKIE_DOMAINS: [&str; 2] = ["twitter.com", "x.com"];
+
+/// Cookies, which make up a logged in session.
+const SESSION_COOKIES: [&str; 2] = ["auth_token", "ct0"];
+
+/// A single cookie read from the cookie database of a browser.
+#[derive(Debug)]
+pub struct BrowserCookie {
+    pub name: String,
+    pub value: String,
+    pub host: String,
+    pub path: String,
+    pub secure: bool,
+    /// Expiry as unix timestamp, or `None` for session cookies.
+    pub expires: Option<i64>,
+}
+
+fn home() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// The cookie database of the default profile of the given browser.
+fn default_cookie_database(browser: &str) -> Result<PathBuf, String> {
+    let candidates: Vec<PathBuf> = match browser {
+        "firefox" => {
+            let profile_roots = [
+                home().join(".mozilla/firefox"),
+                home().join("Library/Application Support/Firefox/Profiles"),
+                dirs::config_dir()
+                    .unwrap_or_default()
+                    .join("Mozilla/Firefox/Profiles"),
+            ];
+            profile_roots
+                .iter()
+                .filter_map(|root| std::fs::read_dir(root).ok())
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path().join("cookies.sqlite"))
+                .collect()
+        }
+        "chrome" | "chromium" => {
+            let name = if browser == "chrome" {
+                "google-chrome"
+            } else {
+                "chromium"
+            };
+            let profile = dirs::config_dir()
+                .unwrap_or_default()
+                .join(name)
+                .join("Default");
+            vec![profile.join("Network/Cookies"), profile.join("Cookies")]
+        }
+        _ => return Err(format!("Unsupported browser {}", browser)),
+    };
+
+    candidates
+        .into_iter()
+        .filter(|path| path.exists())
+        .max_by_key(|path| path.metadata().and_then(|meta| meta.modified()).ok())
+        .ok_or_else(|| format!("Could not find a cookie database for {}", browser))
+}
+
+/// Open a copy of the given database, as browsers keep their cookie database locked while running.
+///
+/// The write-ahead log holding recently set cookies is copied along. The copy is placed in a
+/// temporary directory only accessible by the current user, which is removed once the returned
+/// handle is dropped.
+fn open_copy(database: &Path) -> Result<(Connection, tempfile::TempDir), String> {
+    let directory = tempfile::tempdir()
+        .map_err(|err| format!("Could not create temporary directory: {}", err))?;
+    let copy = directory.path().join("cookies.sqlite");
+    for suffix in ["", "-wal", "-shm"] {
+        let mut source = database.as_os_str().to_owned();
+        source.push(suffix);
+        let source = PathBuf::from(source);
+        if !suffix.is_empty() && !source.exists() {
+            continue;
+        }
+        let mut target = copy.as_os_str().to_owned();
+        target.push(suffix);
+        std::fs::copy(&source, target)
+            .map_err(|err| format!("Could not copy cookie database {:?}: {}", source, err))?;
+    }
+    let connection = Connection::open(&copy)
+        .map_err(|err| format!("Could not open cookie database {:?}: {}", database, err))?;
+    Ok((connection, directory))
+}
+
+fn domain_filter(column: &str) -> String {
+    COOKIE_DOMAINS
+        .iter()
+        .map(|domain| format!("{} LIKE '%{}'", column, domain))
+        .collect::<Vec<String>>()
+        .join(" OR ")
+}
+
+fn read_firefox(connection: &Connection) -> rusqlite::Result<Vec<BrowserCookie>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT name, value, host, path, isSecure, expiry FROM moz_cookies WHERE {}",
+        domain_filter("host")
+    ))?;
+    let cookies = statement.query_map([], |row| {
+        let expiry: i64 = row.get(5)?;
+        Ok(BrowserCookie {
+            name: row.get(0)?,
+            value: row.get(1)?,
+            host: row.get(2)?,
+            path: row.get(3)?,
+            secure: row.get(4)?,
+            // Recent Firefox versions store the expiry in milliseconds instead of seconds.
+            expires: Some(if expiry > 100_000_000_000 {
+                expiry / 1000
+            } else {
+                expiry
+            }),
+        })
+    })?;
+    cookies.collect()
+}
+
+/// Decrypt a cookie value stored by Chrome on Linux without a system keyring ("v10" format).
+///
+/// Values encrypted with a key from the system keyring ("v11" on Linux, and all values on macOS
+/// and Windows) are not supported.
+fn decrypt_chrome_value(encrypted: &[u8], strip_domain_hash: bool) -> Option<String> {
+    let encrypted = encrypted.strip_prefix(b"v10")?;
+    let mut key = [0u8; 16];
+    pbkdf2::pbkdf2_hmac::<sha1::Sha1>(b"peanuts", b"saltysalt", 1, &mut key);
+    let iv = [b' '; 16];
+    let decrypted = cbc::Decryptor::<aes::Aes128>::new(&key.into(), &iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(encrypted)
+        .ok()?;
+    // Newer database versions prefix the value with a SHA256 hash of the cookie domain.
+    let value = if strip_domain_hash && decrypted.len() >= 32 {
+        &decrypted[32..]
+    } else {
+        &decrypted[..]
+    };
+    String::from_utf8(value.to_vec()).ok()
+}
+
+fn read_chrome(connection: &Connection) -> rusqlite::Result<Vec<BrowserCookie>> {
+    let version: i64 = connection
+        .query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| {
+            row.get::<_, String>(0)
+        })
+        .map(|version| version.parse().unwrap_or(0))
+        .unwrap_or(0);
+    let mut statement = connection.prepare(&format!(
+        "SELECT name, value, encrypted_value, host_key, path, is_secure, expires_utc FROM cookies WHERE {}",
+        domain_filter("host_key")
+    ))?;
+    let cookies = statement.query_map([], |row| {
+        let value: String = row.get(1)?;
+        let encrypted_value: Vec<u8> = row.get(2)?;
+        let expires_utc: i64 = row.get(6)?;
+        Ok(BrowserCookie {
+            name: row.get(0)?,
+            value: if value.is_empty() {
+                decrypt_chrome_value(&encrypted_value, version >= 24).unwrap_or_default()
+            } else {
+                value
+            },
+            host: row.get(3)?,
+            path: row.get(4)?,
+            secure: row.get(5)?,
+            // Chrome stores microseconds since 1601-01-01.
+            expires: if expires_utc == 0 {
+                None
+            } else {
+                Some(expires_utc / 1_000_000 - 11_644_473_600)
+            },
+        })
+    })?;
+    // Values, which could not be decrypted, are left empty.
+    cookies.collect()
+}
+
+/// Read the twitter session cookies of a browser profile.
+///
+/// The browser is given as `firefox`, `chrome` or `chromium`, optionally followed by the path to
+/// the cookie database of a specific profile (eg. `firefox:/path/to/cookies.sqlite`).
+///
+/// Fails unless the cookies of a logged in session are found, which also covers cookies, which
+/// could not be decrypted.
+pub fn read_cookies(specification: &str) -> Result<Vec<BrowserCookie>, String> {
+    let (browser, database) = match specification.split_once(':') {
+        Some((browser, database)) => (browser, PathBuf::from(database)),
+        None => (specification, default_cookie_database(specification)?),
+    };
+
+    let (connection, directory) = open_copy(&database)?;
+    let cookies = match browser {
+        "firefox" => read_firefox(&connection),
+        "chrome" | "chromium" => read_chrome(&connection),
+        _ => return Err(format!("Unsupported browser {}", browser)),
+    }
+    .map_err(|err| format!("Could not read cookies from {:?}: {}", database, err));
+    drop(connection);
+    drop(directory);
+
+    let (cookies, undecrypted): (Vec<BrowserCookie>, Vec<BrowserCookie>) = cookies?
+        .into_iter()
+        .partition(|cookie| !cookie.value.is_empty());
+    let missing: Vec<&str> = SESSION_COOKIES
+        .iter()
+        .copied()
+        .filter(|name| !cookies.iter().any(|cookie| cookie.name == *name))
+        .collect();
+    if missing.is_empty() {
+        return Ok(cookies);
+    }
+    let missing_cookies = match missing.len() {
+        1 => format!("{} cookie", missing[0]),
+        _ => format!("{} cookies", missing.join(" and ")),
+    };
+    if undecrypted
+        .iter()
+        .any(|cookie| missing.contains(&cookie.name.as_str()))
+    {
+        return Err(format!(
+            "Could not decrypt the {} in {:?}, only cookies of Chrome and Chromium on Linux without a system keyring are supported",
+            missing_cookies,
+            database
+        ));
+    }
+    Err(format!(
+        "No {} of twitter.com or x.com found in {:?}, log in with {} first",
+        missing_cookies, database, browser
+    ))
+}
@@ -0,0 +1,96 @@
+use url::Url;
+
+/// Map the different spellings of a twitter media url to a single canonical key.
+///
+/// The same asset is reachable as `http://pbs.twimg.com/media/ID.jpg`,
+/// `https://pbs.twimg.com/media/ID.jpg:orig`, `https://pbs.twimg.com/media/ID?format=jpg&name=large`
+/// and several more variants, which all map to `pbs.twimg.com/media/ID.jpg`.
+///
+/// Urls, which can not be parsed, are used as their own key.
+pub fn media_key(url: &str) -> String {
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return url.to_string(),
+    };
+    let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+
+    // Strip size suffixes like `:orig` or `:large`.
+    let path = parsed.path();
+    let path = match path.rsplit_once(':') {
+        Some((path, _size)) => path,
+        None => path,
+    };
+
+    let format = parsed
+        .query_pairs()
+        .find(|(key, _)| key == "format")
+        .map(|(_, value)| value.to_ascii_lowercase());
+    let has_extension = path
+        .rsplit('/')
+        .next()
+        .map(|name| name.contains('.'))
+        .unwrap_or(false);
+
+    match format {
+        Some(format) if !has_extension => format!("{}{}.{}", host, path, format),
+        _ => format!("{}{}", host, path),
+    }
+}
+
+/// Url of the given size variant (eg. `small` or `orig`) of a twitter media url.
+///
+/// Urls not pointing to the twitter media host are returned unchanged.
+pub fn variant_url(url: &str, size: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) if parsed.host_str() == Some("pbs.twimg.com") => {
+            format!("https://{}:{}", media_key(url), size)
+        }
+        _ => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_all_spellings_of_a_media_url_to_the_same_key() {
+        for url in [
+            "http://pbs.twimg.com/media/ABC.jpg",
+            "https://pbs.twimg.com/media/ABC.jpg",
+            "https://PBS.twimg.com/media/ABC.jpg",
+            "https://pbs.twimg.com/media/ABC.jpg:orig",
+            "https://pbs.twimg.com/media/ABC.jpg:large",
+            "https://pbs.twimg.com/media/ABC?format=jpg&name=large",
+            "https://pbs.twimg.com/media/ABC?name=orig&format=JPG",
+            "https://pbs.twimg.com/media/ABC.jpg?name=small",
+        ] {
+            assert_eq!(media_key(url), "pbs.twimg.com/media/ABC.jpg", "{}", url);
+        }
+    }
+
+    #[test]
+    fn keeps_different_formats_apart() {
+        assert_ne!(
+            media_key("https://pbs.twimg.com/media/ABC?format=png"),
+            media_key("https://pbs.twimg.com/media/ABC?format=jpg")
+        );
+    }
+
+    #[test]
+    fn uses_unparsable_urls_as_their_own_key() {
+        assert_eq!(media_key("not a url"), "not a url");
+    }
+
+    #[test]
+    fn builds_size_variants_of_media_urls() {
+        assert_eq!(
+            variant_url("http://pbs.twimg.com/media/ABC.jpg", "small"),
+            "https://pbs.twimg.com/media/ABC.jpg:small"
+        );
+        assert_eq!(
+            variant_url("https://video.twimg.com/ext_tw_video/1/vid.mp4", "small"),
+            "https://video.twimg.com/ext_tw_video/1/vid.mp4"
+        );
+    }
+}
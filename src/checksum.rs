@@ -0,0 +1,71 @@
+use md5::Md5;
+use reqwest::header::HeaderMap;
+use sha2::{Digest, Sha256};
+
+/// Hash functions a server may announce the checksum of a response with.
+#[derive(Debug, Clone, Copy)]
+enum Algorithm {
+    Md5,
+    Sha256,
+}
+
+/// Checksum of a download as announced by the server.
+#[derive(Debug, Clone)]
+pub struct Checksum {
+    algorithm: Algorithm,
+    expected: Vec<u8>,
+}
+
+impl Checksum {
+    /// The checksum announced in the given response headers, if any.
+    ///
+    /// A `Digest` header covers the complete file, while `Content-MD5` only covers the body of the
+    /// response, which is useless for a resumed partial response.
+    pub fn announced(headers: &HeaderMap, partial: bool) -> Option<Checksum> {
+        // Digest: sha-256=<base64>,md5=<base64>
+        let digest = headers
+            .get("digest")
+            .and_then(|digest| digest.to_str().ok())
+            .into_iter()
+            .flat_map(|digest| digest.split(','))
+            .filter_map(|entry| {
+                let (name, value) = entry.trim().split_once('=')?;
+                let algorithm = match name.to_ascii_lowercase().as_str() {
+                    "sha-256" => Algorithm::Sha256,
+                    "md5" => Algorithm::Md5,
+                    _ => return None,
+                };
+                Some((algorithm, value))
+            })
+            .next();
+        let content_md5 = headers
+            .get("content-md5")
+            .filter(|_| !partial)
+            .and_then(|content_md5| content_md5.to_str().ok())
+            .map(|value| (Algorithm::Md5, value));
+        let (algorithm, value) = digest.or(content_md5)?;
+        Some(Checksum {
+            algorithm,
+            expected: base64::decode(value.trim()).ok()?,
+        })
+    }
+
+    /// Whether the given data matches the checksum.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        let actual = match self.algorithm {
+            Algorithm::Md5 => Md5::digest(data).to_vec(),
+            Algorithm::Sha256 => Sha256::digest(data).to_vec(),
+        };
+        actual == self.expected
+    }
+}
+
+impl std::fmt::Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.algorithm {
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha256 => "sha-256",
+        };
+        write!(f, "{} {}", name, base64::encode(&self.expected))
+    }
+}
@@ -0,0 +1,179 @@
+use crate::canonical;
+use crate::dump::TweetDump;
+use crate::heap_stats;
+use crate::interrupt;
+use crate::progress::Spinner;
+use crate::summary;
+use crate::Media;
+use futures::channel::mpsc::UnboundedSender;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Interval, in which the url list is flushed to disk.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// File, which receives the url of every image as soon as it is found.
+struct UrlList {
+    filename: String,
+    writer: BufWriter<File>,
+    last_flush: Instant,
+}
+
+impl UrlList {
+    fn create(filename: &str) -> UrlList {
+        let file = File::create(filename)
+            .unwrap_or_else(|_| panic!("Could not open file for writing {}", filename));
+        UrlList {
+            filename: filename.to_string(),
+            writer: BufWriter::new(file),
+            last_flush: Instant::now(),
+        }
+    }
+
+    fn append(&mut self, url: &str) {
+        writeln!(self.writer, "{}", url)
+            .unwrap_or_else(|_| panic!("Could not write to file {}", self.filename));
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        self.writer
+            .flush()
+            .unwrap_or_else(|_| panic!("Could not write to file {}", self.filename));
+        self.last_flush = Instant::now();
+    }
+}
+
+/// Collects the images found while retrieving a timeline and passes them on to the downloads
+/// right away.
+///
+/// Images reachable through multiple retweets, quotes or sources are only collected once, from the
+/// source retrieved first. If a url list is requested, urls are written to it while retrieving,
+/// so an interrupted run still leaves a usable partial list behind. The same goes for the tweets
+/// written to a tweet dump.
+pub struct Collector {
+    max_image_count: u32,
+    max_tweet_count: u32,
+    count: usize,
+    /// Number of tweets scanned, whether they carried collected media or not.
+    tweets: u32,
+    /// Canonical key of every collected media.
+    seen_media: HashSet<String>,
+    url_list: Option<UrlList>,
+    tweet_dump: Option<TweetDump>,
+    spinner: Spinner,
+    sender: UnboundedSender<Media>,
+    limit_reached: Arc<AtomicBool>,
+}
+
+/// Tells whether the retrieval stopped at the maximum number of images or tweets, and therefore
+/// left older tweets behind. Remains usable after the collector is finished.
+#[derive(Clone)]
+pub struct LimitReached(Arc<AtomicBool>);
+
+impl LimitReached {
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Collector {
+    /// Create a collector for up to `max_image_count` images out of up to `max_tweet_count` tweets
+    /// (0 meaning unlimited), which are sent to the given channel.
+    pub fn new(
+        max_image_count: u32,
+        max_tweet_count: u32,
+        url_list: Option<&str>,
+        tweet_dump: Option<TweetDump>,
+        spinner: Spinner,
+        sender: UnboundedSender<Media>,
+    ) -> Collector {
+        Collector {
+            max_image_count,
+            max_tweet_count,
+            count: 0,
+            tweets: 0,
+            seen_media: HashSet::new(),
+            url_list: url_list.map(UrlList::create),
+            tweet_dump,
+            spinner,
+            sender,
+            limit_reached: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Add an image, unless the same media has been collected before.
+    pub fn add(&mut self, media: Media) {
+        if !self.seen_media.insert(canonical::media_key(&media.url)) {
+            return;
+        }
+        if let Some(url_list) = &mut self.url_list {
+            url_list.append(&media.url);
+        }
+        self.count += 1;
+        heap_stats::set_collected_media(self.count);
+        self.sender
+            .unbounded_send(media)
+            .expect("Could not pass image on to the downloads");
+    }
+
+    /// Spinner displaying the progress of the retrieval.
+    pub fn spinner(&self) -> Spinner {
+        self.spinner.clone()
+    }
+
+    /// Store the complete data of a tweet carrying collected media, if a tweet dump is requested.
+    pub fn dump(&self, tweet: &egg_mode::tweet::Tweet) {
+        if let Some(tweet_dump) = &self.tweet_dump {
+            tweet_dump.write(tweet);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Count a scanned tweet.
+    pub fn scanned(&mut self) {
+        self.tweets += 1;
+        summary::tweet_scanned();
+    }
+
+    pub fn tweets(&self) -> u32 {
+        self.tweets
+    }
+
+    /// Whether the maximum number of images has been collected or tweets have been scanned, or the
+    /// run has been interrupted and no further images are needed.
+    pub fn is_full(&self) -> bool {
+        if (self.max_image_count > 0 && self.count >= self.max_image_count as usize)
+            || (self.max_tweet_count > 0 && self.tweets >= self.max_tweet_count)
+        {
+            self.limit_reached.store(true, Ordering::Relaxed);
+            return true;
+        }
+        interrupt::requested()
+    }
+
+    /// Whether the retrieval stopped at the maximum number of images or tweets.
+    pub fn limit_reached(&self) -> LimitReached {
+        LimitReached(self.limit_reached.clone())
+    }
+
+    /// Flush the url list and close the channel, which ends the downloads once they are done.
+    pub fn finish(mut self) {
+        if let Some(url_list) = &mut self.url_list {
+            url_list.flush();
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use twitter_image_downloader::{create_private_file, netrc};
+
+/// A complete set of twitter API credentials.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credentials {
+    pub consumer_key: String,
+    pub consumer_secret: String,
+    pub access_token: String,
+    pub access_token_secret: String,
+}
+
+/// Settings of a single account.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Account {
+    /// Kinds of media to download (eg. `photos+gifs`).
+    pub media: Option<String>,
+}
+
+/// Persistent configuration, which provides defaults for values not given on the commandline.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    pub consumer_key: Option<String>,
+    pub consumer_secret: Option<String>,
+    pub access_token: Option<String>,
+    pub access_token_secret: Option<String>,
+    /// Additional credentials, which are rotated through whenever a rate limit is hit.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<Credentials>,
+    /// Accounts, which are followed for the archive.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watch: Vec<String>,
+    /// Settings for individual accounts, keyed by username.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub accounts: HashMap<String, Account>,
+}
+
+/// Location of the configuration file, if none is given explicitly.
+pub fn default_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("twitter_image_downloader")
+        .join("config.toml")
+}
+
+impl Config {
+    /// Load the configuration from the given file, falling back to an empty configuration if the
+    /// file does not exist.
+    pub fn load(path: &Path) -> Config {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)
+                .unwrap_or_else(|err| panic!("Could not parse config file {:?}: {}", path, err)),
+            Err(_) => Config::default(),
+        }
+    }
+
+    /// Fill all credentials, which are not configured, from a `.netrc` entry.
+    ///
+    /// The entry provides the consumer key as `login`, the consumer secret as `password` and the
+    /// access token and its secret as `account`, separated by a colon.
+    pub fn apply_netrc(&mut self, entry: netrc::Entry) {
+        if self.consumer_key.is_none() {
+            self.consumer_key = entry.login;
+        }
+        if self.consumer_secret.is_none() {
+            self.consumer_secret = entry.password;
+        }
+        if let Some((access_token, access_token_secret)) = entry
+            .account
+            .as_deref()
+            .and_then(|account| account.split_once(':'))
+        {
+            if self.access_token.is_none() {
+                self.access_token = Some(access_token.to_string());
+            }
+            if self.access_token_secret.is_none() {
+                self.access_token_secret = Some(access_token_secret.to_string());
+            }
+        }
+    }
+
+    /// Settings of the given account, matching its username case insensitively.
+    pub fn account(&self, username: &str) -> Option<&Account> {
+        self.accounts
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(username))
+            .map(|(_, account)| account)
+    }
+
+    /// Write the configuration to the given file, creating its parent directories if necessary.
+    ///
+    /// The file holds the credentials, therefore it is only readable by the current user.
+    pub fn save(&self, path: &Path) {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .unwrap_or_else(|_| panic!("Could not create directory {:?}", parent));
+        }
+        let contents = toml::to_string(self).expect("Could not serialize config");
+        create_private_file(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .unwrap_or_else(|_| panic!("Could not write config file {:?}", path));
+    }
+}
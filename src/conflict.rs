@@ -0,0 +1,94 @@
+use crate::{manifest, sidecar};
+use std::path::Path;
+
+/// What to do, if the target file of a download already exists.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Conflict {
+    /// Keep the existing file and do not download the image.
+    Skip,
+    /// Replace the existing file.
+    Overwrite,
+    /// Store the image under a numbered name like `name (1).jpg`.
+    Rename,
+    /// Abort the run.
+    Error,
+}
+
+impl Conflict {
+    pub fn parse(policy: &str) -> Result<Conflict, String> {
+        match policy {
+            "skip" => Ok(Conflict::Skip),
+            "overwrite" => Ok(Conflict::Overwrite),
+            "rename" => Ok(Conflict::Rename),
+            "error" => Ok(Conflict::Error),
+            _ => Err(format!(
+                "Unknown conflict policy '{}' (use skip, overwrite, rename or error)",
+                policy
+            )),
+        }
+    }
+}
+
+/// The given relative path with a number appended to the file name: `name (1).jpg`.
+pub fn numbered(relative_path: &str, number: usize) -> String {
+    let (directory, file_name) = match relative_path.rsplit_once('/') {
+        Some((directory, file_name)) => (format!("{}/", directory), file_name),
+        None => (String::new(), relative_path),
+    };
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            format!("{}{} ({}).{}", directory, stem, number, extension)
+        }
+        _ => format!("{}{} ({})", directory, file_name, number),
+    }
+}
+
+/// Whether the existing file at the given path (relative to the output directory) has been
+/// downloaded from the given url before, according to its metadata sidecar or the manifest.
+pub fn is_same_download(
+    output_directory: &str,
+    relative_path: &str,
+    url: &str,
+    download_url: &str,
+    manifest: Option<&manifest::Manifest>,
+) -> bool {
+    let path = format!("{}/{}", output_directory, relative_path);
+    let sidecar_matches = sidecar::read(&sidecar::path(Path::new(&path)))
+        .and_then(|metadata| metadata.media_url)
+        .is_some_and(|media_url| media_url == url);
+    sidecar_matches
+        || manifest
+            .and_then(|manifest| manifest.url_by_path(relative_path))
+            .is_some_and(|recorded| recorded == url || recorded == download_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_the_file_name_before_its_extension() {
+        assert_eq!(numbered("image.jpg", 1), "image (1).jpg");
+        assert_eq!(
+            numbered("user/2021/image.jpg", 12),
+            "user/2021/image (12).jpg"
+        );
+        assert_eq!(numbered("archive.tar.gz", 2), "archive.tar (2).gz");
+    }
+
+    #[test]
+    fn numbers_file_names_without_extension_at_the_end() {
+        assert_eq!(numbered("image", 1), "image (1)");
+        assert_eq!(numbered("user/.hidden", 1), "user/.hidden (1)");
+        assert_eq!(numbered("user.name/image", 3), "user.name/image (3)");
+    }
+
+    #[test]
+    fn parses_policies() {
+        assert_eq!(Conflict::parse("skip"), Ok(Conflict::Skip));
+        assert_eq!(Conflict::parse("overwrite"), Ok(Conflict::Overwrite));
+        assert_eq!(Conflict::parse("rename"), Ok(Conflict::Rename));
+        assert_eq!(Conflict::parse("error"), Ok(Conflict::Error));
+        assert!(Conflict::parse("Skip").is_err());
+    }
+}
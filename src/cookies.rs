@@ -0,0 +1,155 @@
+use crate::browser::BrowserCookie;
+use reqwest::StatusCode;
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+/// Name of the cookie, which twitter uses to transport the CSRF token.
+const CSRF_COOKIE: &str = "ct0";
+
+/// Name of the file, which the cookie jar is persisted in.
+const COOKIE_FILE: &str = "cookies.json";
+
+/// Directory the cookie jar is persisted in by default.
+///
+/// The jar holds the session cookies, therefore it is kept out of the output directory, which may
+/// be served to others.
+pub fn default_directory() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("twitter_image_downloader")
+}
+
+/// Cookie jar of the media client, which is persisted between runs.
+pub struct CookieJar {
+    store: Arc<CookieStoreMutex>,
+    path: PathBuf,
+}
+
+impl CookieJar {
+    /// Load the cookie jar from the given directory, starting with an empty one if none has been
+    /// stored yet.
+    ///
+    /// A cookie jar, which older versions persisted in `previous_directory`, is taken over and
+    /// removed from there.
+    pub fn load(directory: &Path, previous_directory: Option<&Path>) -> CookieJar {
+        let path = directory.join(COOKIE_FILE);
+        let previous_path = previous_directory
+            .map(|previous_directory| previous_directory.join(COOKIE_FILE))
+            .filter(|previous_path| previous_path.exists());
+        let source = match &previous_path {
+            Some(previous_path) if !path.exists() => previous_path,
+            _ => &path,
+        };
+        let store = match File::open(source) {
+            Ok(file) => CookieStore::load_json(BufReader::new(file))
+                .unwrap_or_else(|_| panic!("Could not read cookie jar {:?}", source)),
+            Err(_) => CookieStore::default(),
+        };
+
+        let cookie_jar = CookieJar {
+            store: Arc::new(CookieStoreMutex::new(store)),
+            path,
+        };
+        if let Some(previous_path) = previous_path {
+            cookie_jar.save();
+            std::fs::remove_file(&previous_path)
+                .unwrap_or_else(|_| panic!("Could not remove file {:?}", previous_path));
+        }
+        cookie_jar
+    }
+
+    /// Persist the current state of the cookie jar, only readable by the current user.
+    pub fn save(&self) {
+        let mut file = crate::create_private_file(&self.path)
+            .unwrap_or_else(|_| panic!("Could not open file for writing {:?}", self.path));
+        self.store
+            .lock()
+            .unwrap()
+            .save_incl_expired_and_nonpersistent_json(&mut file)
+            .unwrap_or_else(|_| panic!("Could not write cookie jar {:?}", self.path));
+    }
+
+    /// Add cookies read from a browser profile to the jar.
+    pub fn import(&self, cookies: &[BrowserCookie]) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or(0);
+        let mut store = self.store.lock().unwrap();
+        for cookie in cookies {
+            let host = cookie.host.trim_start_matches('.');
+            let url = match Url::parse(&format!("https://{}{}", host, cookie.path)) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+            let mut header = format!(
+                "{}={}; Domain={}; Path={}",
+                cookie.name, cookie.value, host, cookie.path
+            );
+            if cookie.secure {
+                header.push_str("; Secure");
+            }
+            if let Some(expires) = cookie.expires {
+                header.push_str(&format!("; Max-Age={}", expires - now));
+            }
+            let _ = store.parse(&header, &url);
+        }
+    }
+
+    /// Create a client builder, which stores and sends cookies using this jar.
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        reqwest::Client::builder().cookie_provider(self.store.clone())
+    }
+
+    /// The CSRF token currently stored for the given url, if any.
+    pub fn csrf_token(&self, url: &Url) -> Option<String> {
+        self.store
+            .lock()
+            .unwrap()
+            .matches(url)
+            .into_iter()
+            .find(|cookie| cookie.name() == CSRF_COOKIE)
+            .map(|cookie| cookie.value().to_string())
+    }
+
+    /// Issue a GET request, which carries the current CSRF token of the jar.
+    ///
+    /// Twitter rotates the CSRF token by setting a new cookie on a rejected request. In that case
+    /// the request is repeated once using the refreshed token.
+    pub async fn get(
+        &self,
+        client: &reqwest::Client,
+        url: &Url,
+    ) -> reqwest::Result<reqwest::Response> {
+        let token = self.csrf_token(url);
+        let response = self.csrf_request(client, url, &token).send().await?;
+        if response.status() != StatusCode::FORBIDDEN {
+            return Ok(response);
+        }
+
+        let refreshed_token = self.csrf_token(url);
+        if refreshed_token.is_none() || refreshed_token == token {
+            return Ok(response);
+        }
+        self.csrf_request(client, url, &refreshed_token)
+            .send()
+            .await
+    }
+
+    fn csrf_request(
+        &self,
+        client: &reqwest::Client,
+        url: &Url,
+        token: &Option<String>,
+    ) -> reqwest::RequestBuilder {
+        let request = client.get(url.clone());
+        match token {
+            Some(token) => request.header("x-csrf-token", token),
+            None => request,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::Media;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+
+/// Spreadsheet friendly list of all downloaded media.
+pub struct CsvExport {
+    path: String,
+    writer: Mutex<BufWriter<File>>,
+}
+
+/// Quote a field if it contains separators, quotes or line breaks.
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl CsvExport {
+    pub fn create(path: &str) -> Result<CsvExport, String> {
+        let file =
+            File::create(path).map_err(|err| format!("Could not create {}: {}", path, err))?;
+        let export = CsvExport {
+            path: path.to_string(),
+            writer: Mutex::new(BufWriter::new(file)),
+        };
+        export.write_row(&["tweet_id", "date", "tweet_url", "media_url", "filename"]);
+        Ok(export)
+    }
+
+    fn write_row(&self, fields: &[&str]) {
+        let row = fields
+            .iter()
+            .map(|value| field(value))
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(self.writer.lock().unwrap(), "{}", row)
+            .unwrap_or_else(|err| panic!("Could not write {}: {}", self.path, err));
+    }
+
+    /// Add the given media, which has been saved under the given filename.
+    pub fn add(&self, media: &Media, filename: &str) {
+        let date = media
+            .created_at
+            .map(|created_at| created_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        self.write_row(&[
+            &media.tweet_id.to_string(),
+            &date,
+            &media.tweet_url(),
+            &media.url,
+            filename,
+        ]);
+    }
+
+    pub fn finish(self) {
+        let path = self.path;
+        self.writer
+            .into_inner()
+            .unwrap()
+            .flush()
+            .unwrap_or_else(|err| panic!("Could not write {}: {}", path, err));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::config::Config;
+use crate::exit_code;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use twitter_image_downloader::auth::prompt;
+use twitter_image_downloader::sidecar;
+
+/// Rank the accounts, whose images the given user retweeted or quoted, by the number of their
+/// tweets found in the archive.
+fn rank(output_directory: &Path, username: &str) -> Vec<(String, usize)> {
+    let paths = sidecar::find(output_directory);
+
+    let mut tweets: HashMap<String, HashSet<u64>> = HashMap::new();
+    for metadata in paths.iter().filter_map(|path| sidecar::read(path)) {
+        if metadata.author.eq_ignore_ascii_case(username)
+            && !metadata.original_author.eq_ignore_ascii_case(username)
+        {
+            tweets
+                .entry(metadata.original_author)
+                .or_default()
+                .insert(metadata.tweet_id);
+        }
+    }
+
+    let mut ranking: Vec<(String, usize)> = tweets
+        .into_iter()
+        .map(|(account, tweets)| (account, tweets.len()))
+        .collect();
+    ranking.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranking
+}
+
+/// Show the image posting accounts most retweeted or quoted by the given archived user and offer
+/// to add them to the watch list.
+///
+/// The archive has to be downloaded with `--write-metadata` and `--include-retweets`.
+pub fn discover(config_path: &Path, output_directory: &Path, username: &str, limit: usize) {
+    let ranking = rank(output_directory, username);
+    if ranking.is_empty() {
+        eprintln!(
+            "No retweeted or quoted images of {} found in {:?}. Download them using --include-retweets and --write-metadata first.",
+            username, output_directory
+        );
+        std::process::exit(exit_code::USAGE_ERROR);
+    }
+
+    let mut config = Config::load(config_path);
+    let mut changed = false;
+    println!("Accounts most retweeted or quoted by {}:", username);
+    for (account, count) in ranking.into_iter().take(limit) {
+        let watched = config
+            .watch
+            .iter()
+            .any(|watched| watched.eq_ignore_ascii_case(&account));
+        if watched {
+            println!("  {:>5}  {} (watched)", count, account);
+            continue;
+        }
+        println!("  {:>5}  {}", count, account);
+        let add = prompt(&format!("Add {} to the watch list? [y/N] ", account))
+            .map(|answer| answer.eq_ignore_ascii_case("y"))
+            .unwrap_or(false);
+        if add {
+            config.watch.push(account);
+            changed = true;
+        }
+    }
+
+    if changed {
+        config.save(config_path);
+        println!("Watch list written to {:?}", config_path);
+    }
+}
@@ -0,0 +1,27 @@
+use egg_mode::tweet::Tweet;
+use std::path::{Path, PathBuf};
+
+/// Directory receiving the complete data of every tweet, which carried downloaded media.
+pub struct TweetDump {
+    directory: PathBuf,
+}
+
+impl TweetDump {
+    pub fn create(directory: &Path) -> TweetDump {
+        std::fs::create_dir_all(directory)
+            .unwrap_or_else(|_| panic!("Could not create directory {:?}", directory));
+        TweetDump {
+            directory: directory.to_path_buf(),
+        }
+    }
+
+    /// Store the given tweet (including retweeted and quoted tweets) as `tweet_<id>.json`.
+    pub fn write(&self, tweet: &Tweet) {
+        let path = self.directory.join(format!("tweet_{}.json", tweet.id));
+        let part_path = self.directory.join(format!("tweet_{}.json.part", tweet.id));
+        let contents = serde_json::to_vec_pretty(tweet).expect("Could not serialize tweet");
+        std::fs::write(&part_path, contents)
+            .and_then(|_| std::fs::rename(&part_path, &path))
+            .unwrap_or_else(|_| panic!("Could not write file {:?}", path));
+    }
+}
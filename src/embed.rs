@@ -0,0 +1,167 @@
+use crate::Media;
+use img_parts::jpeg::{markers, JpegSegment};
+use img_parts::png::PngChunk;
+use img_parts::{Bytes, DynImage, ImageEXIF};
+
+const IMAGE_DESCRIPTION: u16 = 0x010e;
+const ARTIST: u16 = 0x013b;
+const XP_COMMENT: u16 = 0x9c9c;
+
+const ASCII: u16 = 2;
+const BYTE: u16 = 1;
+
+/// Signature of EXIF data stored in a JPEG APP1 segment.
+const EXIF_SIGNATURE: &[u8] = b"Exif\0\0";
+
+/// Signature of XMP packets stored in a JPEG APP1 segment.
+const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Keyword of the PNG iTXt chunk holding XMP packets.
+const XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+
+/// Build a big endian TIFF structure with a single IFD holding the given entries.
+///
+/// Entries need to be sorted by tag.
+fn tiff(entries: &[(u16, u16, Vec<u8>)]) -> Vec<u8> {
+    let ifd_size = 2 + entries.len() * 12 + 4;
+    let mut data_offset = 8 + ifd_size;
+
+    let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+    let mut data = vec![];
+    tiff.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+    for (tag, kind, value) in entries {
+        tiff.extend_from_slice(&tag.to_be_bytes());
+        tiff.extend_from_slice(&kind.to_be_bytes());
+        tiff.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        if value.len() <= 4 {
+            let mut inline = value.clone();
+            inline.resize(4, 0);
+            tiff.extend_from_slice(&inline);
+        } else {
+            tiff.extend_from_slice(&(data_offset as u32).to_be_bytes());
+            data.extend_from_slice(value);
+            // Values have to start on word boundaries.
+            if value.len() % 2 == 1 {
+                data.push(0);
+            }
+            data_offset = 8 + ifd_size + data.len();
+        }
+    }
+    // No further IFDs
+    tiff.extend_from_slice(&[0, 0, 0, 0]);
+    tiff.extend_from_slice(&data);
+    tiff
+}
+
+fn ascii(value: &str) -> Vec<u8> {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+/// Windows XP* tags are stored as null terminated UCS-2 little endian.
+fn ucs2(value: &str) -> Vec<u8> {
+    value
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect()
+}
+
+fn exif(media: &Media) -> Vec<u8> {
+    let mut entries = vec![];
+    if let Some(text) = &media.text {
+        entries.push((IMAGE_DESCRIPTION, ASCII, ascii(text)));
+    }
+    entries.push((ARTIST, ASCII, ascii(&media.original_author)));
+    entries.push((XP_COMMENT, BYTE, ucs2(&media.tweet_url())));
+    tiff(&entries)
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xmp(media: &Media) -> String {
+    let description = match &media.text {
+        Some(text) => format!(
+            "<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:description>",
+            escape_xml(text)
+        ),
+        None => String::new(),
+    };
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+         <rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+         {}\
+         <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>\
+         <dc:source>{}</dc:source>\
+         </rdf:Description>\
+         </rdf:RDF>\
+         </x:xmpmeta>\
+         <?xpacket end=\"w\"?>",
+        description,
+        escape_xml(&media.original_author),
+        escape_xml(&media.tweet_url())
+    )
+}
+
+/// Embed the tweet text, author and url into the given image.
+///
+/// The text is stored as EXIF ImageDescription and XMP dc:description, the author as EXIF Artist
+/// and XMP dc:creator and the tweet url as EXIF XPComment and XMP dc:source. XMP is only written
+/// to JPEG and PNG files. Images, which can not be parsed, are returned unchanged.
+pub fn embed(image: Bytes, media: &Media) -> Bytes {
+    let mut parsed = match DynImage::from_bytes(image.clone()) {
+        Ok(Some(parsed)) => parsed,
+        _ => return image,
+    };
+    let exif = exif(media);
+    let xmp = xmp(media);
+    match &mut parsed {
+        DynImage::Jpeg(jpeg) => {
+            jpeg.set_exif(None);
+            let segments = jpeg.segments_mut();
+            segments.retain(|segment| {
+                segment.marker() != markers::APP1 || !segment.contents().starts_with(XMP_SIGNATURE)
+            });
+            // EXIF and XMP directly follow the JFIF header
+            let position = match segments.first() {
+                Some(segment) if segment.marker() == markers::APP0 => 1,
+                _ => 0,
+            };
+            let mut exif_contents = EXIF_SIGNATURE.to_vec();
+            exif_contents.extend_from_slice(&exif);
+            let mut xmp_contents = XMP_SIGNATURE.to_vec();
+            xmp_contents.extend_from_slice(xmp.as_bytes());
+            segments.splice(
+                position..position,
+                [
+                    JpegSegment::new_with_contents(markers::APP1, Bytes::from(exif_contents)),
+                    JpegSegment::new_with_contents(markers::APP1, Bytes::from(xmp_contents)),
+                ],
+            );
+        }
+        DynImage::Png(png) => {
+            png.set_exif(Some(Bytes::from(exif)));
+            let chunks = png.chunks_mut();
+            chunks.retain(|chunk| {
+                &chunk.kind() != b"iTXt" || !chunk.contents().starts_with(XMP_KEYWORD)
+            });
+            // Keyword, no compression, empty language tag and translated keyword
+            let mut contents = XMP_KEYWORD.to_vec();
+            contents.extend_from_slice(&[0, 0, 0, 0, 0]);
+            contents.extend_from_slice(xmp.as_bytes());
+            chunks.insert(1, PngChunk::new(*b"iTXt", Bytes::from(contents)));
+        }
+        DynImage::WebP(webp) => webp.set_exif(Some(Bytes::from(exif))),
+    }
+
+    parsed.encoder().bytes()
+}
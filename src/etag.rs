@@ -0,0 +1,104 @@
+use reqwest::header::{self, HeaderMap};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Name of the cache database inside the state directory.
+const CACHE_FILE: &str = "etags.db";
+
+/// Validators of a downloaded file, which allow to ask the server whether it changed since.
+#[derive(Debug, Clone)]
+pub struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    /// The validators sent by the server with a response, if any.
+    pub fn of(headers: &HeaderMap) -> Option<Validators> {
+        let value = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let validators = Validators {
+            etag: value(header::ETAG),
+            last_modified: value(header::LAST_MODIFIED),
+        };
+        if validators.etag.is_none() && validators.last_modified.is_none() {
+            return None;
+        }
+        Some(validators)
+    }
+
+    /// Turn the given request into one, which is answered with `304 Not Modified` if the file did
+    /// not change.
+    pub fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut request = request;
+        if let Some(etag) = &self.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// ETag and Last-Modified of every downloaded url, stored as SQLite database inside the state
+/// directory.
+pub struct Cache {
+    connection: Mutex<Connection>,
+}
+
+impl Cache {
+    pub fn open(state_directory: &Path) -> Cache {
+        let path = state_directory.join(CACHE_FILE);
+        let connection = Connection::open(&path)
+            .unwrap_or_else(|err| panic!("Could not open ETag cache {:?}: {}", path, err));
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS validators (
+                    url TEXT PRIMARY KEY,
+                    etag TEXT,
+                    last_modified TEXT
+                );",
+            )
+            .expect("Could not initialize ETag cache");
+
+        Cache {
+            connection: Mutex::new(connection),
+        }
+    }
+
+    /// The validators of the most recent download of the given url.
+    pub fn get(&self, url: &str) -> Option<Validators> {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT etag, last_modified FROM validators WHERE url = ?1",
+                params![url],
+                |row| {
+                    Ok(Validators {
+                        etag: row.get(0)?,
+                        last_modified: row.get(1)?,
+                    })
+                },
+            )
+            .optional()
+            .expect("Could not read ETag cache")
+    }
+
+    pub fn set(&self, url: &str, validators: &Validators) {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO validators (url, etag, last_modified) VALUES (?1, ?2, ?3)",
+                params![url, validators.etag, validators.last_modified],
+            )
+            .expect("Could not write ETag cache");
+    }
+}
@@ -0,0 +1,86 @@
+use crate::ndjson::Status;
+use crate::progress::{self, Mode};
+use crate::source::Source;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::Write;
+use tracing::info;
+
+/// Something that happened during a run, reported as JSON object with `--json-progress`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// A page of tweets has been retrieved.
+    PageFetched {
+        source: Source,
+        page: u32,
+        tweets: usize,
+    },
+    /// The download of a file started.
+    FileStarted { url: &'a str },
+    /// A file has been processed, with the outcome recorded in the manifest.
+    FileDone {
+        url: &'a str,
+        path: &'a str,
+        status: Status,
+        bytes: Option<u64>,
+    },
+    /// Something went wrong, eg. a download failed.
+    Error {
+        url: Option<&'a str>,
+        message: &'a str,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct Line<'a> {
+    time: DateTime<Utc>,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+/// Record the given event in the log, eg. the `--log-file`.
+fn log(event: &Event) {
+    match event {
+        Event::PageFetched {
+            source,
+            page,
+            tweets,
+        } => info!(
+            "Fetched page {} of the {} with {} tweets",
+            page,
+            source.name(),
+            tweets
+        ),
+        Event::FileStarted { url } => info!("Downloading {}", url),
+        Event::FileDone {
+            url,
+            path,
+            status,
+            bytes,
+        } => match bytes {
+            Some(bytes) => info!("{:?}: {} as {} ({} bytes)", status, url, path, bytes),
+            None => info!("{:?}: {} as {}", status, url, path),
+        },
+        Event::Error {
+            url: Some(url),
+            message,
+        } => info!("Error with {}: {}", url, message),
+        Event::Error { url: None, message } => info!("Error: {}", message),
+    }
+}
+
+/// Log the given event and print it as a single line of JSON to stdout, if JSON progress is
+/// requested.
+pub fn emit(event: Event) {
+    log(&event);
+    if progress::mode() != Mode::Json {
+        return;
+    }
+    let line = serde_json::to_string(&Line {
+        time: Utc::now(),
+        event,
+    })
+    .expect("Could not serialize event");
+    let _ = writeln!(std::io::stdout().lock(), "{}", line);
+}
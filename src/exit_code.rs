@@ -0,0 +1,37 @@
+use twitter_image_downloader::{auth, retrieval};
+
+/// The credentials are missing or were rejected.
+pub const AUTH_FAILURE: i32 = 1;
+/// The account does not exist, is suspended or is protected.
+pub const USER_UNAVAILABLE: i32 = 2;
+/// Some images could not be downloaded, or the timeline could only be retrieved partially.
+pub const PARTIAL_FAILURE: i32 = 3;
+/// A file or database could not be read or written.
+pub const IO_ERROR: i32 = 4;
+/// The commandline arguments are invalid, or do not leave anything to do (`EX_USAGE`).
+pub const USAGE_ERROR: i32 = 64;
+/// Something failed unexpectedly, which is reported by panicking (`EX_SOFTWARE`).
+pub const INTERNAL_ERROR: i32 = 70;
+/// The run has been interrupted with Ctrl-C, and images are left for `--resume`.
+pub const INTERRUPTED: i32 = 130;
+
+/// Exit code of a run, whose retrieval ended with the given error.
+pub fn of_retrieval(err: &retrieval::Error) -> i32 {
+    match err {
+        retrieval::Error::Unauthorized => AUTH_FAILURE,
+        retrieval::Error::Api(err) if auth::is_expired(err) => AUTH_FAILURE,
+        retrieval::Error::NotFound(_)
+        | retrieval::Error::Suspended(_)
+        | retrieval::Error::Protected(_) => USER_UNAVAILABLE,
+        retrieval::Error::Api(_) | retrieval::Error::Request(_) => PARTIAL_FAILURE,
+    }
+}
+
+/// Exit with `INTERNAL_ERROR` if anything panics, instead of aborting.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        std::process::exit(INTERNAL_ERROR);
+    }));
+}
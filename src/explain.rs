@@ -0,0 +1,228 @@
+use crate::exit_code;
+use egg_mode::tweet::Tweet;
+use std::collections::HashMap;
+use std::path::Path;
+use twitter_image_downloader::filter::{MediaKinds, TweetFilter, Verdict};
+use twitter_image_downloader::naming::NamingStrategy;
+use twitter_image_downloader::{
+    canonical, manifest, media_sources, retrieval, screen_name, tweet_media,
+};
+
+/// Id of the tweet given as url (`https://twitter.com/<user>/status/<id>`) or plain id.
+fn tweet_id(tweet: &str) -> Option<u64> {
+    let id = match tweet.split_once("/status/") {
+        Some((_, path)) => path.split(['/', '?', '#']).next()?,
+        None => tweet,
+    };
+    id.parse().ok()
+}
+
+/// Retrieve the given tweet through the API.
+pub async fn lookup(tweet: &str, token: &egg_mode::Token) -> Tweet {
+    let id = tweet_id(tweet).unwrap_or_else(|| {
+        eprintln!(
+            "Invalid tweet {} (use https://twitter.com/<USERNAME>/status/<ID> or the id)",
+            tweet
+        );
+        std::process::exit(exit_code::USAGE_ERROR);
+    });
+    match egg_mode::tweet::show(id, token).await {
+        Ok(tweet) => tweet.response,
+        Err(err) => {
+            eprintln!("Could not retrieve tweet {}: {}", id, err);
+            std::process::exit(exit_code::of_retrieval(&retrieval::Error::Api(err)));
+        }
+    }
+}
+
+/// Describe the outcome of the date filter for the given tweet.
+fn explain_dates(tweet: &Tweet, filter: &TweetFilter) -> String {
+    let format = "%Y-%m-%d %H:%M UTC";
+    match filter.check_date(tweet.created_at) {
+        Verdict::Accept => "pass".to_string(),
+        Verdict::Skip => format!(
+            "fail, posted after {} (--older-than), the tweet would be skipped",
+            filter.older_than.unwrap().format(format)
+        ),
+        Verdict::Stop => format!(
+            "fail, posted before {} (--newer-than), the retrieval would stop here",
+            filter.newer_than.unwrap().format(format)
+        ),
+    }
+}
+
+/// Describe the outcome of the engagement filter for the given tweet.
+fn explain_engagement(tweet: &Tweet, filter: &TweetFilter) -> String {
+    let original = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+    match filter.check_engagement(tweet) {
+        Verdict::Accept => "pass".to_string(),
+        _ => format!(
+            "fail, {} likes / {} retweets (--min-likes / --min-retweets), the tweet would be skipped",
+            original.favorite_count, original.retweet_count
+        ),
+    }
+}
+
+/// Describe the outcome of the text filters for the given tweet.
+fn explain_text(tweet: &Tweet, filter: &TweetFilter) -> String {
+    match filter.check_text(tweet) {
+        Verdict::Accept => "pass".to_string(),
+        _ => "fail, the text does not match --match-text or matches --exclude-text or --exclude-keywords, the tweet would be skipped".to_string(),
+    }
+}
+
+/// Describe the outcome of the hashtag filter for the given tweet.
+fn explain_hashtags(tweet: &Tweet, filter: &TweetFilter) -> String {
+    match filter.check_hashtags(tweet) {
+        Verdict::Accept => "pass".to_string(),
+        _ => format!(
+            "fail, none of #{} (--with-hashtag), the tweet would be skipped",
+            filter.hashtags.join(", #")
+        ),
+    }
+}
+
+/// Describe the outcome of the sensitive content filter for the given tweet.
+fn explain_sensitive(tweet: &Tweet, filter: &TweetFilter) -> String {
+    match (filter.check_sensitive(tweet), filter.sensitive) {
+        (Verdict::Accept, _) => "pass".to_string(),
+        (_, Some(true)) => {
+            "fail, not flagged as possibly sensitive (--only-sensitive), the tweet would be skipped"
+                .to_string()
+        }
+        _ => "fail, flagged as possibly sensitive (--skip-sensitive), the tweet would be skipped"
+            .to_string(),
+    }
+}
+
+/// Print how a download run would treat the given tweet.
+pub fn explain(
+    tweet: &Tweet,
+    filter: &TweetFilter,
+    naming: &dyn NamingStrategy,
+    output_directory: &Path,
+) {
+    let username = screen_name(tweet).unwrap_or_default();
+    println!(
+        "Tweet {} by @{}, posted {}",
+        tweet.id,
+        username,
+        tweet.created_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    for (index, source) in media_sources(tweet).iter().enumerate() {
+        if source.id == tweet.id {
+            continue;
+        }
+        let relation = if index == 0 { "retweeted" } else { "quoted" };
+        println!(
+            "  contains {} tweet {} by @{}, its media is attributed to that author",
+            relation,
+            source.id,
+            screen_name(source).unwrap_or_default()
+        );
+    }
+
+    println!();
+    println!("Filters:");
+    let passes = filter.check(tweet) == Verdict::Accept;
+    println!("  date: {}", explain_dates(tweet, filter));
+    println!("  engagement: {}", explain_engagement(tweet, filter));
+    println!("  text: {}", explain_text(tweet, filter));
+    println!("  hashtags: {}", explain_hashtags(tweet, filter));
+    println!("  sensitive: {}", explain_sensitive(tweet, filter));
+    if let Some(withheld) = twitter_image_downloader::withheld::of(tweet) {
+        println!("  restriction: the tweet is {}", withheld);
+    }
+
+    println!();
+    println!("Media:");
+    let kinds = [
+        ("photo", filter.media_kinds.photos, MediaKinds::PHOTOS),
+        (
+            "gif",
+            filter.media_kinds.gifs,
+            MediaKinds {
+                photos: false,
+                gifs: true,
+                videos: false,
+            },
+        ),
+        (
+            "video",
+            filter.media_kinds.videos,
+            MediaKinds {
+                photos: false,
+                gifs: false,
+                videos: true,
+            },
+        ),
+    ];
+    let manifest = if manifest::Manifest::exists(output_directory) {
+        Some(manifest::Manifest::open(output_directory))
+    } else {
+        None
+    };
+    let photos = tweet_media(tweet, &username, &MediaKinds::PHOTOS).len();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut number = 0;
+    for (kind, selected, only) in kinds.iter() {
+        for media in tweet_media(tweet, &username, only) {
+            number += 1;
+            println!("  {}. {} {}", number, kind, media.url);
+            if !selected {
+                println!("     skipped, --media does not include {}s", kind);
+                continue;
+            }
+            if !passes {
+                println!("     skipped, the tweet does not pass the filters");
+                continue;
+            }
+            if filter.check_photo_count(photos) != Verdict::Accept {
+                println!(
+                    "     skipped, the tweet carries {} photos (--min-photos-per-tweet)",
+                    photos
+                );
+                continue;
+            }
+            if filter.check_format(&media.url) != Verdict::Accept {
+                println!("     skipped, --formats does not include its format");
+                continue;
+            }
+            if filter.check_dimensions(media.width, media.height) != Verdict::Accept {
+                println!(
+                    "     skipped, {}x{} pixels are below --min-width / --min-height",
+                    media.width.unwrap_or_default(),
+                    media.height.unwrap_or_default()
+                );
+                continue;
+            }
+            if let Some(first) = seen.get(&canonical::media_key(&media.url)) {
+                println!("     skipped, the same media as entry {}", first);
+                continue;
+            }
+            seen.insert(canonical::media_key(&media.url), number);
+
+            let file_name = media.url.rsplit('/').next().unwrap_or_default();
+            let relative_path = naming.relative_path(&media, file_name);
+            println!("     file: {}", relative_path);
+            let verdict = if output_directory.join(&relative_path).exists() {
+                "the file already exists, it would be skipped (unless --on-conflict or --redownload say otherwise)"
+            } else if manifest
+                .as_ref()
+                .and_then(|manifest| manifest.url_by_path(&relative_path))
+                .is_some()
+            {
+                "recorded in the archive database, but the file is missing, it would be downloaded"
+            } else {
+                "new, it would be downloaded"
+            };
+            println!("     {}", verdict);
+        }
+    }
+    if number == 0 {
+        println!("  none found");
+    } else if manifest.is_some() {
+        println!();
+        println!("Duplicates of already archived content are only detected once the media is downloaded.");
+    }
+}
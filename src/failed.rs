@@ -0,0 +1,100 @@
+use crate::progress;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Name of the failure report inside the output directory.
+pub const FAILED_FILE: &str = "failed_urls.txt";
+
+/// Images, which could not be downloaded, together with their tweet, the path they were to be
+/// stored at and the reason.
+#[derive(Default)]
+pub struct Failures {
+    entries: Mutex<Vec<(String, u64, String, String)>>,
+}
+
+impl Failures {
+    pub fn add(&self, url: &str, tweet_id: u64, path: &str, reason: &str) {
+        self.entries.lock().unwrap().push((
+            url.to_string(),
+            tweet_id,
+            path.to_string(),
+            reason.to_string(),
+        ));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+
+    /// Write the failed urls to `failed_urls.txt` inside the output directory, one url followed
+    /// by the tweet id, the path and the reason per line, separated by tabs.
+    ///
+    /// The report of an earlier run is removed, if nothing failed.
+    pub fn write(&self, output_directory: &Path) {
+        let path = output_directory.join(FAILED_FILE);
+        let mut entries = self.entries.lock().unwrap();
+        if entries.is_empty() {
+            let _ = std::fs::remove_file(&path);
+            return;
+        }
+        entries.sort();
+        let contents: String = entries
+            .iter()
+            .map(|(url, tweet_id, path, reason)| {
+                format!("{}\t{}\t{}\t{}\n", url, tweet_id, path, reason)
+            })
+            .collect();
+        std::fs::write(&path, contents)
+            .unwrap_or_else(|_| panic!("Could not write file {:?}", path));
+        progress::status(format!(
+            "{} images could not be downloaded, see {:?}",
+            entries.len(),
+            path
+        ));
+    }
+}
+
+/// An image listed in a failure report.
+#[derive(Debug, PartialEq)]
+pub struct FailedDownload {
+    pub url: String,
+    /// Tweet and path are missing in reports of older versions, which only list url and reason.
+    pub tweet_id: Option<u64>,
+    pub path: Option<String>,
+}
+
+/// The images listed in a failure report written by an earlier run.
+pub fn read(path: &Path) -> Result<Vec<FailedDownload>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read failure report {:?}: {}", path, err))?;
+    Ok(parse(&contents))
+}
+
+fn parse(contents: &str) -> Vec<FailedDownload> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+            let url = fields[0];
+            if url.is_empty() {
+                return None;
+            }
+            let tweet_id = match fields.as_slice() {
+                [_, tweet_id, _, _, ..] => tweet_id.parse().ok(),
+                _ => None,
+            };
+            Some(FailedDownload {
+                url: url.to_string(),
+                tweet_id,
+                path: tweet_id
+                    .and(fields.get(2))
+                    .filter(|path| !path.is_empty())
+                    .map(|path| path.to_string()),
+            })
+        })
+        .collect()
+}
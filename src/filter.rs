@@ -0,0 +1,373 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use regex::Regex;
+
+/// Decision about a single tweet during timeline retrieval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    /// Collect the media of the tweet.
+    Accept,
+    /// Ignore the tweet, but continue with the next one.
+    Skip,
+    /// Ignore the tweet and stop paging, as no further tweet can match.
+    Stop,
+}
+
+/// Kinds of media to collect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaKinds {
+    pub photos: bool,
+    pub gifs: bool,
+    pub videos: bool,
+}
+
+impl Default for MediaKinds {
+    fn default() -> Self {
+        MediaKinds {
+            photos: true,
+            gifs: false,
+            videos: false,
+        }
+    }
+}
+
+impl MediaKinds {
+    pub const PHOTOS: MediaKinds = MediaKinds {
+        photos: true,
+        gifs: false,
+        videos: false,
+    };
+
+    /// Parse a combination of media kinds like `photos`, `photos+gifs` or `all`.
+    pub fn parse(kinds: &str) -> Result<MediaKinds, String> {
+        let mut result = MediaKinds {
+            photos: false,
+            gifs: false,
+            videos: false,
+        };
+        for kind in kinds.split(['+', ',']) {
+            match kind.trim() {
+                "photos" | "photo" => result.photos = true,
+                "gifs" | "gif" => result.gifs = true,
+                "videos" | "video" => result.videos = true,
+                "all" => {
+                    result.photos = true;
+                    result.gifs = true;
+                    result.videos = true;
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown media kind '{}' (use photos, gifs, videos or all)",
+                        kind
+                    ))
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Criteria, which tweets need to fulfil for their media to be collected.
+#[derive(Debug, Default)]
+pub struct TweetFilter {
+    pub newer_than: Option<DateTime<Utc>>,
+    pub older_than: Option<DateTime<Utc>>,
+    /// Only tweets newer than this one are of interest (eg. because older ones were already
+    /// retrieved by a previous run).
+    pub since_id: Option<u64>,
+    /// Only tweets up to and including this one are of interest.
+    pub max_id: Option<u64>,
+    /// Minimum number of likes of the tweet carrying the media.
+    pub min_likes: Option<i32>,
+    /// Minimum number of retweets of the tweet carrying the media.
+    pub min_retweets: Option<i32>,
+    /// Pattern the text of a tweet needs to match.
+    pub match_text: Option<Regex>,
+    /// Pattern the text of a tweet must not match.
+    pub exclude_text: Option<Regex>,
+    /// Terms (lowercase), which must not appear in the text of a tweet.
+    pub exclude_keywords: Vec<String>,
+    /// Minimum width of an image in pixels.
+    pub min_width: Option<i32>,
+    /// Minimum height of an image in pixels.
+    pub min_height: Option<i32>,
+    /// Only collect tweets flagged as possibly sensitive (`true`) or only unflagged ones (`false`).
+    pub sensitive: Option<bool>,
+    /// Minimum number of photos a tweet needs to carry.
+    pub min_photos: Option<usize>,
+    /// Formats of the files to collect (eg. `jpg`), all if empty.
+    pub formats: Vec<String>,
+    /// Hashtags (lowercase, without `#`), of which a tweet needs to carry at least one.
+    pub hashtags: Vec<String>,
+    pub media_kinds: MediaKinds,
+}
+
+/// Parse a relative duration like `12h`, `30d`, `2w`, `6m` or `1y`.
+///
+/// Months are counted as 30 and years as 365 days. Durations reaching back further than the
+/// earliest representable date are rejected.
+pub fn parse_age(age: &str) -> Result<Duration, String> {
+    let split = age
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("Missing unit in duration '{}' (use h, d, w, m or y)", age))?;
+    let (amount, unit) = age.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}'", age))?;
+    let hours: i64 = match unit {
+        "h" => 1,
+        "d" => 24,
+        "w" => 7 * 24,
+        "m" => 30 * 24,
+        "y" => 365 * 24,
+        _ => {
+            return Err(format!(
+                "Unknown unit '{}' in duration '{}' (use h, d, w, m or y)",
+                unit, age
+            ))
+        }
+    };
+    amount
+        .checked_mul(hours * 60 * 60)
+        // Durations are limited to i64::MAX milliseconds.
+        .filter(|seconds| *seconds <= i64::MAX / 1000)
+        .map(Duration::seconds)
+        .filter(|duration| Utc::now().checked_sub_signed(*duration).is_some())
+        .ok_or_else(|| format!("Duration '{}' is too long", age))
+}
+
+/// Parse a calendar date like `2021-03-14`, either as the start or the end of that day (UTC).
+pub fn parse_date(date: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}' (use YYYY-MM-DD)", date))?;
+    let time = if end_of_day {
+        date.and_hms(23, 59, 59)
+    } else {
+        date.and_hms(0, 0, 0)
+    };
+    Ok(DateTime::from_utc(time, Utc))
+}
+
+/// Lowercase name of a file format, with common aliases unified (eg. `JPEG` becomes `jpg`).
+pub fn normalize_format(format: &str) -> String {
+    match format.trim().to_lowercase().as_str() {
+        "jpeg" => "jpg".to_string(),
+        format => format.to_string(),
+    }
+}
+
+/// Format of the file behind a media url, taken from its `format` parameter (eg.
+/// `?format=webp&name=orig`) or its extension.
+pub fn url_format(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    if let Some((_, format)) = url.query_pairs().find(|(name, _)| name == "format") {
+        return Some(normalize_format(&format));
+    }
+    let file_name = url.path_segments()?.next_back()?;
+    let (_, extension) = file_name.rsplit_once('.')?;
+    Some(normalize_format(extension))
+}
+
+/// Format of a file served with the given Content-Type (eg. `image/png`). Generic types like
+/// `application/octet-stream` do not tell anything about the format.
+pub fn content_type_format(content_type: &str) -> Option<String> {
+    let essence = content_type.split(';').next()?.trim();
+    match essence.split_once('/')? {
+        ("image", subtype) | ("video", subtype) => Some(normalize_format(subtype)),
+        _ => None,
+    }
+}
+
+impl TweetFilter {
+    /// Check the creation date of a tweet.
+    ///
+    /// Timelines are retrieved newest first, therefore the first tweet older than the lower bound
+    /// ends the retrieval.
+    pub fn check_date(&self, created_at: DateTime<Utc>) -> Verdict {
+        if let Some(newer_than) = self.newer_than {
+            if created_at < newer_than {
+                return Verdict::Stop;
+            }
+        }
+        if let Some(older_than) = self.older_than {
+            if created_at > older_than {
+                return Verdict::Skip;
+            }
+        }
+        Verdict::Accept
+    }
+
+    /// Check the id of a tweet.
+    ///
+    /// Tweet ids increase over time, therefore the first tweet not newer than `since_id` ends the
+    /// retrieval.
+    pub fn check_id(&self, id: u64) -> Verdict {
+        match (self.since_id, self.max_id) {
+            (Some(since_id), _) if id <= since_id => Verdict::Stop,
+            (_, Some(max_id)) if id > max_id => Verdict::Skip,
+            _ => Verdict::Accept,
+        }
+    }
+
+    /// Check the likes and retweets of a tweet.
+    ///
+    /// A retweet itself is never liked or retweeted, therefore the engagement of the retweeted
+    /// tweet counts.
+    pub fn check_engagement(&self, tweet: &egg_mode::tweet::Tweet) -> Verdict {
+        let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+        if self.min_likes.is_some_and(|min| tweet.favorite_count < min)
+            || self
+                .min_retweets
+                .is_some_and(|min| tweet.retweet_count < min)
+        {
+            return Verdict::Skip;
+        }
+        Verdict::Accept
+    }
+
+    /// Check the text of a tweet, or the retweeted one.
+    pub fn check_text(&self, tweet: &egg_mode::tweet::Tweet) -> Verdict {
+        let text = &tweet.retweeted_status.as_deref().unwrap_or(tweet).text;
+        if self
+            .match_text
+            .as_ref()
+            .is_some_and(|pattern| !pattern.is_match(text))
+            || self
+                .exclude_text
+                .as_ref()
+                .is_some_and(|pattern| pattern.is_match(text))
+        {
+            return Verdict::Skip;
+        }
+        let text = text.to_lowercase();
+        if self
+            .exclude_keywords
+            .iter()
+            .any(|keyword| text.contains(keyword.as_str()))
+        {
+            return Verdict::Skip;
+        }
+        Verdict::Accept
+    }
+
+    /// Check the hashtags of a tweet, or the retweeted one.
+    pub fn check_hashtags(&self, tweet: &egg_mode::tweet::Tweet) -> Verdict {
+        if self.hashtags.is_empty() {
+            return Verdict::Accept;
+        }
+        let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+        let tagged = tweet
+            .entities
+            .hashtags
+            .iter()
+            .any(|hashtag| self.hashtags.contains(&hashtag.text.to_lowercase()));
+        if tagged {
+            Verdict::Accept
+        } else {
+            Verdict::Skip
+        }
+    }
+
+    /// Check the dimensions of a single image. Images of unknown size are accepted.
+    pub fn check_dimensions(&self, width: Option<i32>, height: Option<i32>) -> Verdict {
+        let too_small = |size: Option<i32>, min: Option<i32>| matches!((size, min), (Some(size), Some(min)) if size < min);
+        if too_small(width, self.min_width) || too_small(height, self.min_height) {
+            return Verdict::Skip;
+        }
+        Verdict::Accept
+    }
+
+    /// Check the format of a single file by its url. Files of unknown format are accepted.
+    pub fn check_format(&self, url: &str) -> Verdict {
+        match url_format(url) {
+            Some(format) if !self.formats.is_empty() && !self.formats.contains(&format) => {
+                Verdict::Skip
+            }
+            _ => Verdict::Accept,
+        }
+    }
+
+    /// Check the possibly sensitive flag of a tweet, or the retweeted one.
+    pub fn check_sensitive(&self, tweet: &egg_mode::tweet::Tweet) -> Verdict {
+        let tweet = tweet.retweeted_status.as_deref().unwrap_or(tweet);
+        match self.sensitive {
+            Some(sensitive) if tweet.possibly_sensitive.unwrap_or(false) != sensitive => {
+                Verdict::Skip
+            }
+            _ => Verdict::Accept,
+        }
+    }
+
+    /// Check the number of photos carried by a tweet.
+    pub fn check_photo_count(&self, photos: usize) -> Verdict {
+        match self.min_photos {
+            Some(min_photos) if photos < min_photos => Verdict::Skip,
+            _ => Verdict::Accept,
+        }
+    }
+
+    /// Check a tweet against all configured criteria.
+    pub fn check(&self, tweet: &egg_mode::tweet::Tweet) -> Verdict {
+        [
+            self.check_id(tweet.id),
+            self.check_date(tweet.created_at),
+            self.check_engagement(tweet),
+            self.check_text(tweet),
+            self.check_hashtags(tweet),
+            self.check_sensitive(tweet),
+        ]
+        .iter()
+        .copied()
+        .find(|verdict| *verdict != Verdict::Accept)
+        .unwrap_or(Verdict::Accept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parses_ages_in_every_unit() {
+        assert_eq!(parse_age("12h"), Ok(Duration::hours(12)));
+        assert_eq!(parse_age("30d"), Ok(Duration::days(30)));
+        assert_eq!(parse_age("2w"), Ok(Duration::weeks(2)));
+        assert_eq!(parse_age("6m"), Ok(Duration::days(180)));
+        assert_eq!(parse_age("1y"), Ok(Duration::days(365)));
+    }
+
+    #[test]
+    fn rejects_ages_without_valid_unit() {
+        assert!(parse_age("12").is_err());
+        assert!(parse_age("12x").is_err());
+        assert!(parse_age("12dd").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("").is_err());
+    }
+
+    #[test]
+    fn rejects_ages_which_overflow() {
+        assert!(parse_age("99999999999999999999d").is_err());
+        assert!(parse_age("9223372036854775807h").is_err());
+        assert!(parse_age("999999999y").is_err());
+    }
+
+    #[test]
+    fn parses_dates_as_start_or_end_of_day() {
+        assert_eq!(
+            parse_date("2021-03-14", false),
+            Ok(Utc.ymd(2021, 3, 14).and_hms(0, 0, 0))
+        );
+        assert_eq!(
+            parse_date("2021-03-14", true),
+            Ok(Utc.ymd(2021, 3, 14).and_hms(23, 59, 59))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        assert!(parse_date("2021-02-30", false).is_err());
+        assert!(parse_date("14.03.2021", false).is_err());
+        assert!(parse_date("", false).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use egg_mode::tweet::Tweet;
+use std::path::PathBuf;
+
+/// Recording or replaying of timeline pages, used for offline testing and reproducible bug
+/// reports.
+pub enum Fixtures {
+    Disabled,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+fn page_file(directory: &std::path::Path, page: u32) -> PathBuf {
+    directory.join(format!("timeline_page_{:04}.json", page))
+}
+
+/// Remove all information about the authenticated account from a tweet.
+fn sanitize(tweet: &mut Tweet) {
+    tweet.current_user_retweet = None;
+    tweet.favorited = None;
+    tweet.retweeted = None;
+    if let Some(retweeted_status) = tweet.retweeted_status.as_mut() {
+        sanitize(retweeted_status);
+    }
+    if let Some(quoted_status) = tweet.quoted_status.as_mut() {
+        sanitize(quoted_status);
+    }
+}
+
+impl Fixtures {
+    pub fn is_replay(&self) -> bool {
+        matches!(self, Fixtures::Replay(_))
+    }
+
+    /// Store a sanitized copy of a retrieved timeline page.
+    pub fn record(&self, page: u32, feed: &[Tweet]) {
+        if let Fixtures::Record(directory) = self {
+            std::fs::create_dir_all(directory)
+                .unwrap_or_else(|_| panic!("Could not create directory {:?}", directory));
+            let mut feed = feed.to_vec();
+            feed.iter_mut().for_each(sanitize);
+            let path = page_file(directory, page);
+            let contents = serde_json::to_vec_pretty(&feed).expect("Could not serialize tweets");
+            std::fs::write(&path, contents)
+                .unwrap_or_else(|_| panic!("Could not write file {:?}", path));
+        }
+    }
+
+    /// Load a previously recorded timeline page, or `None` if there are no more pages.
+    pub fn replay(&self, page: u32) -> Option<Vec<Tweet>> {
+        match self {
+            Fixtures::Replay(directory) => {
+                let path = page_file(directory, page);
+                let contents = std::fs::read(&path).ok()?;
+                Some(
+                    serde_json::from_slice(&contents)
+                        .unwrap_or_else(|err| panic!("Invalid fixture {:?}: {}", path, err)),
+                )
+            }
+            _ => None,
+        }
+    }
+}
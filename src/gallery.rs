@@ -0,0 +1,242 @@
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use twitter_image_downloader::{progress, sidecar, walk};
+
+/// Directory inside the output directory, which holds the generated thumbnails.
+pub const THUMBNAIL_DIRECTORY: &str = ".thumbnails";
+
+/// Name of the generated gallery page inside the output directory.
+pub const GALLERY_FILE: &str = "index.html";
+
+/// Default edge length of the thumbnails in pixels.
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 320;
+
+/// Extensions of the media files, which thumbnails can be generated for.
+const IMAGE_EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "gif", "webp"];
+
+fn thumbnail_path(output_directory: &Path, relative_path: &str) -> PathBuf {
+    output_directory
+        .join(THUMBNAIL_DIRECTORY)
+        .join(relative_path)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        IMAGE_EXTENSIONS
+            .iter()
+            .any(|known| extension.eq_ignore_ascii_case(known))
+    })
+}
+
+/// Write a thumbnail of the given image, which fits into a square of the given size.
+pub fn write_thumbnail(output_directory: &Path, image: &Path, size: u32) -> Result<(), String> {
+    let relative_path = walk::relative_path(output_directory, image);
+    let target = thumbnail_path(output_directory, &relative_path);
+    let failed = |err: &dyn std::fmt::Display| format!("{}: {}", relative_path, err);
+
+    // The content decides about the format, as twitter serves some PNG images as `.jpg`.
+    let thumbnail = image::ImageReader::open(image)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|err| failed(&err))?
+        .decode()
+        .map_err(|err| failed(&err))?
+        .thumbnail(size, size);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|err| failed(&err))?;
+    }
+    let format = image::ImageFormat::from_path(&target).map_err(|err| failed(&err))?;
+    let thumbnail = match format {
+        // JPEG can not store transparency.
+        image::ImageFormat::Jpeg => image::DynamicImage::ImageRgb8(thumbnail.to_rgb8()),
+        _ => thumbnail,
+    };
+    let mut part_path = target.clone().into_os_string();
+    part_path.push(".part");
+    let mut file = std::fs::File::create(&part_path).map_err(|err| failed(&err))?;
+    thumbnail
+        .write_to(&mut file, format)
+        .map_err(|err| failed(&err))?;
+    std::fs::rename(&part_path, &target).map_err(|err| failed(&err))
+}
+
+/// Generate the thumbnails of all archived images, using all CPU cores.
+///
+/// Existing thumbnails are only replaced, if `only_missing` is not set. Returns the number of
+/// generated thumbnails.
+pub fn write_thumbnails(output_directory: &Path, size: u32, only_missing: bool) -> usize {
+    let images: Vec<PathBuf> = walk::files(output_directory, &IMAGE_EXTENSIONS)
+        .into_iter()
+        .filter(|image| {
+            !only_missing
+                || !thumbnail_path(
+                    output_directory,
+                    &walk::relative_path(output_directory, image),
+                )
+                .is_file()
+        })
+        .collect();
+    let generated = AtomicUsize::new(0);
+    images.par_iter().for_each(
+        |image| match write_thumbnail(output_directory, image, size) {
+            Ok(()) => {
+                generated.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(err) => tracing::warn!("Could not generate thumbnail of {}", err),
+        },
+    );
+    generated.into_inner()
+}
+
+/// Escape text for use in HTML content and attribute values.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Percent encode the segments of a relative path for use in a link.
+fn link(relative_path: &str) -> String {
+    relative_path
+        .split('/')
+        .map(|segment| {
+            url::form_urlencoded::byte_serialize(segment.as_bytes())
+                .collect::<String>()
+                .replace('+', "%20")
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A single tile of the gallery.
+struct Item {
+    relative_path: String,
+    thumbnail: Option<String>,
+    is_image: bool,
+    metadata: Option<sidecar::Metadata>,
+}
+
+fn render_item(item: &Item) -> String {
+    let source = link(item.thumbnail.as_deref().unwrap_or(&item.relative_path));
+    let preview = if item.is_image {
+        format!(r#"<img src="{}" loading="lazy" alt="">"#, source)
+    } else {
+        format!(
+            r#"<video src="{}" preload="metadata" muted></video>"#,
+            source
+        )
+    };
+    let caption = match &item.metadata {
+        Some(metadata) => format!(
+            r#"<a href="https://twitter.com/{author}/status/{id}">@{author}</a>{date}<p>{text}</p>"#,
+            author = escape(&metadata.original_author),
+            id = metadata.tweet_id,
+            date = metadata
+                .created_at
+                .map(|created_at| format!(" · {}", created_at.format("%Y-%m-%d")))
+                .unwrap_or_default(),
+            text = escape(metadata.text.as_deref().unwrap_or_default()),
+        ),
+        None => escape(&item.relative_path),
+    };
+    format!(
+        "<figure><a href=\"{}\">{}</a><figcaption>{}</figcaption></figure>\n",
+        link(&item.relative_path),
+        preview,
+        caption
+    )
+}
+
+/// Write a static HTML page showing all archived media, newest first.
+///
+/// Thumbnails are used where they have been generated. Returns the number of shown items.
+pub fn write_gallery(output_directory: &Path) -> usize {
+    let mut items: Vec<Item> = walk::files(output_directory, &walk::MEDIA_EXTENSIONS)
+        .into_iter()
+        .map(|path| {
+            let relative_path = walk::relative_path(output_directory, &path);
+            let thumbnail = Some(format!("{}/{}", THUMBNAIL_DIRECTORY, relative_path))
+                .filter(|_| thumbnail_path(output_directory, &relative_path).is_file());
+            Item {
+                metadata: sidecar::read(&sidecar::path(&path)),
+                is_image: is_image(&path),
+                thumbnail,
+                relative_path,
+            }
+        })
+        .collect();
+    items.sort_by(|a, b| {
+        let created_at = |item: &Item| item.metadata.as_ref().and_then(|m| m.created_at);
+        created_at(b)
+            .cmp(&created_at(a))
+            .then_with(|| a.relative_path.cmp(&b.relative_path))
+    });
+
+    let tiles: String = items.iter().map(render_item).collect();
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Twitter Image Archive</title>
+<style>
+body {{ font-family: sans-serif; margin: 1em; background: #f5f8fa; }}
+main {{ display: grid; grid-template-columns: repeat(auto-fill, minmax(240px, 1fr)); gap: 1em; }}
+figure {{ margin: 0; background: #fff; border-radius: 4px; overflow: hidden; }}
+figure img, figure video {{ width: 100%; height: 240px; object-fit: cover; display: block; }}
+figcaption {{ padding: 0.5em; font-size: 0.85em; }}
+figcaption p {{ margin: 0.3em 0 0; }}
+</style>
+</head>
+<body>
+<h1>Twitter Image Archive ({count} items)</h1>
+<main>
+{tiles}</main>
+</body>
+</html>
+"#,
+        count = items.len(),
+        tiles = tiles
+    );
+
+    let path = output_directory.join(GALLERY_FILE);
+    let part_path = output_directory.join(format!("{}.part", GALLERY_FILE));
+    std::fs::write(&part_path, page)
+        .and_then(|_| std::fs::rename(&part_path, &path))
+        .unwrap_or_else(|_| panic!("Could not write file {:?}", path));
+    items.len()
+}
+
+/// Rebuild the thumbnails and/or the gallery page of an archive from the original files.
+pub fn regen(output_directory: &Path, gallery: bool, thumbnails: bool, thumbnail_size: u32) {
+    if thumbnails {
+        let generated = write_thumbnails(output_directory, thumbnail_size, false);
+        println!(
+            "Generated {} thumbnails in {:?}",
+            generated,
+            output_directory.join(THUMBNAIL_DIRECTORY)
+        );
+    }
+    if gallery {
+        let count = write_gallery(output_directory);
+        println!(
+            "Wrote gallery of {} items to {:?}",
+            count,
+            output_directory.join(GALLERY_FILE)
+        );
+    }
+}
+
+/// Bring the gallery of an archive up to date after a download run.
+///
+/// Only thumbnails of new images are generated.
+pub fn update(output_directory: &Path) {
+    write_thumbnails(output_directory, DEFAULT_THUMBNAIL_SIZE, true);
+    let count = write_gallery(output_directory);
+    progress::status(format!(
+        "Updated gallery of {} items in {:?}",
+        count,
+        output_directory.join(GALLERY_FILE)
+    ));
+}
@@ -0,0 +1,72 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::Instrument;
+
+/// Interval, in which the statistics are logged.
+const INTERVAL: Duration = Duration::from_secs(10);
+
+/// System allocator, which keeps track of the allocated memory.
+///
+/// Needs to be installed as `#[global_allocator]` by the binary for the statistics to be collected,
+/// which the binary only does with the `heap-stats` feature.
+pub struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+static COLLECTED_MEDIA: AtomicUsize = AtomicUsize::new(0);
+static PENDING_DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc(layout);
+        if !pointer.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        ALLOCATIONS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub fn set_collected_media(count: usize) {
+    COLLECTED_MEDIA.store(count, Ordering::Relaxed);
+}
+
+pub fn download_queued() {
+    PENDING_DOWNLOADS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn download_finished() {
+    PENDING_DOWNLOADS.fetch_sub(1, Ordering::Relaxed);
+}
+
+fn megabytes(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+/// Periodically log the allocator statistics and queue depths.
+pub fn start() {
+    let logging = async move {
+        loop {
+            tokio::time::sleep(INTERVAL).await;
+            tracing::info!(
+                "Heap: {:.1} MiB in {} allocations (peak {:.1} MiB), {} collected media, {} pending downloads",
+                megabytes(ALLOCATED.load(Ordering::Relaxed)),
+                ALLOCATIONS.load(Ordering::Relaxed),
+                megabytes(PEAK.load(Ordering::Relaxed)),
+                COLLECTED_MEDIA.load(Ordering::Relaxed),
+                PENDING_DOWNLOADS.load(Ordering::Relaxed),
+            );
+        }
+    };
+    tokio::spawn(logging.in_current_span());
+}
@@ -0,0 +1,82 @@
+use crate::config::Config;
+use crate::exit_code;
+use std::path::Path;
+use twitter_image_downloader::auth::{prompt, read_answer};
+
+/// Ask until a value is entered, aborting the setup if the input ends.
+fn required_prompt(message: &str) -> String {
+    loop {
+        match read_answer(message) {
+            Ok(Some(value)) => return value,
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!();
+                eprintln!("Aborted the setup, as no more input could be read: {}", err);
+                std::process::exit(exit_code::USAGE_ERROR);
+            }
+        }
+    }
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(exit_code::AUTH_FAILURE);
+}
+
+/// Interactively ask for the twitter API credentials, verify them and store them in the
+/// configuration file.
+///
+/// If no access token is available yet, one is requested using the PIN based OAuth flow.
+pub async fn init(config_path: &Path) {
+    println!("Setting up Twitter Image Downloader.");
+    println!("The consumer key and secret can be found in your twitter developer portal.");
+    let consumer_key = required_prompt("Consumer Key: ");
+    let consumer_secret = required_prompt("Consumer Secret: ");
+    let consumer = egg_mode::KeyPair::new(consumer_key.clone(), consumer_secret.clone());
+
+    let has_access_token = prompt("Do you already have an access token? [y/N] ")
+        .map(|answer| answer.eq_ignore_ascii_case("y"))
+        .unwrap_or(false);
+
+    let token = if has_access_token {
+        egg_mode::Token::Access {
+            consumer,
+            access: egg_mode::KeyPair::new(
+                required_prompt("Access Token: "),
+                required_prompt("Access Token Secret: "),
+            ),
+        }
+    } else {
+        let request_token = egg_mode::auth::request_token(&consumer, "oob")
+            .await
+            .unwrap_or_else(|err| fail(format!("Could not request an access token: {}", err)));
+        println!(
+            "Open the following url in your browser and authorize the application:\n{}",
+            egg_mode::auth::authorize_url(&request_token)
+        );
+        let pin = required_prompt("PIN: ");
+        let (token, _user_id, _screen_name) =
+            egg_mode::auth::access_token(consumer, &request_token, pin)
+                .await
+                .unwrap_or_else(|err| fail(format!("Could not retrieve access token: {}", err)));
+        token
+    };
+
+    let user = egg_mode::auth::verify_tokens(&token)
+        .await
+        .unwrap_or_else(|err| fail(format!("The credentials could not be verified: {}", err)));
+    println!("Successfully authenticated as @{}", user.screen_name);
+
+    let access = match token {
+        egg_mode::Token::Access { access, .. } => access,
+        egg_mode::Token::Bearer(_) => unreachable!(),
+    };
+    let mut config = Config::load(config_path);
+    config.consumer_key = Some(consumer_key);
+    config.consumer_secret = Some(consumer_secret);
+    config.access_token = Some(access.key.to_string());
+    config.access_token_secret = Some(access.secret.to_string());
+    config.save(config_path);
+
+    println!("Configuration written to {:?}", config_path);
+}
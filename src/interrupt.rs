@@ -0,0 +1,17 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once the run has been asked to stop.
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Stop the run gracefully: the running downloads are finished, but no new ones are started, so
+/// no state is lost.
+///
+/// Returns whether a stop has been requested before.
+pub fn request() -> bool {
+    REQUESTED.swap(true, Ordering::SeqCst)
+}
+
+/// Whether the run has been interrupted.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,1484 @@
+//! Download the images posted by a twitter user.
+//!
+//! `MediaCollector` retrieves the media of a timeline and `Downloader` stores them, the
+//! `twitter_image_downloader` binary is a commandline interface around both.
+//!
+//! Nothing is displayed on the terminal, unless a display is chosen with `progress::set_mode`. A
+//! run is stopped gracefully with `interrupt::request`.
+
+use filter::{TweetFilter, Verdict};
+use futures::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, trace};
+use url::Url;
+
+pub mod archive;
+pub mod auth;
+pub mod browser;
+pub mod canonical;
+mod checksum;
+pub mod collector;
+pub mod conflict;
+pub mod cookies;
+pub mod csv_export;
+pub mod dump;
+mod embed;
+pub mod etag;
+pub mod events;
+pub mod failed;
+pub mod filter;
+pub mod fixtures;
+pub mod heap_stats;
+pub mod interrupt;
+pub mod manifest;
+pub mod naming;
+pub mod ndjson;
+pub mod netrc;
+mod nitter;
+pub mod progress;
+pub mod queue;
+pub mod remote;
+pub mod retrieval;
+pub mod run;
+mod s3;
+pub mod schedule;
+mod sftp;
+pub mod sidecar;
+pub mod source;
+mod staging;
+pub mod summary;
+mod throttle;
+pub mod walk;
+pub mod web;
+mod webdav;
+pub mod withheld;
+
+/// API token of the given application and user credentials.
+pub fn access_token(
+    consumer_key: String,
+    consumer_secret: String,
+    access_token: String,
+    access_token_secret: String,
+) -> egg_mode::Token {
+    let api_token = egg_mode::KeyPair::new(consumer_key, consumer_secret);
+    let access_token = egg_mode::KeyPair::new(access_token, access_token_secret);
+
+    egg_mode::Token::Access {
+        consumer: api_token,
+        access: access_token,
+    }
+}
+
+/// A single image found on a timeline, together with the tweet it was attributed to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Media {
+    pub url: String,
+    pub media_id: Option<u64>,
+    pub tweet_id: u64,
+    pub author: String,
+    pub original_author: String,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub text: Option<String>,
+    pub favorite_count: Option<i32>,
+    pub retweet_count: Option<i32>,
+    pub source: source::Source,
+    /// Restriction of the tweet in some countries.
+    pub withheld: Option<withheld::Withheld>,
+    /// Dimensions of the largest variant offered by twitter, in pixels.
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+impl Media {
+    /// Url of the tweet the image was posted with.
+    pub fn tweet_url(&self) -> String {
+        format!(
+            "https://twitter.com/{}/status/{}",
+            self.original_author, self.tweet_id
+        )
+    }
+}
+
+/// Settings controlling which tweets are requested from a timeline and how.
+#[derive(Clone, Copy)]
+pub struct TimelineOptions {
+    pub include_retweets: bool,
+    /// Include replies to other tweets, which artists often post their work in threads with.
+    pub include_replies: bool,
+    /// Number of tweets requested per page.
+    pub page_size: i32,
+}
+
+fn user_timeline(
+    token: egg_mode::Token,
+    user_id: egg_mode::user::UserID,
+    options: TimelineOptions,
+    source: source::Source,
+) -> egg_mode::tweet::Timeline {
+    let timeline = match source {
+        source::Source::Timeline => egg_mode::tweet::user_timeline(
+            user_id,
+            options.include_replies,
+            options.include_retweets,
+            &token,
+        ),
+        source::Source::Likes => egg_mode::tweet::liked_by(user_id, &token),
+    };
+    timeline.with_page_size(options.page_size)
+}
+
+/// Follow the retweet chain of a tweet down to the tweet, which originally carried the content.
+fn original_tweet(tweet: &egg_mode::tweet::Tweet) -> &egg_mode::tweet::Tweet {
+    match &tweet.retweeted_status {
+        Some(retweeted) => original_tweet(retweeted),
+        None => tweet,
+    }
+}
+
+/// All tweets, which may contribute media to the given timeline entry.
+///
+/// Retweets are resolved to their original tweet, and quoted tweets are followed as well, so that
+/// every image is attributed to the author, who posted it in the first place.
+pub fn media_sources(tweet: &egg_mode::tweet::Tweet) -> Vec<&egg_mode::tweet::Tweet> {
+    let mut sources = vec![];
+    let mut current = Some(original_tweet(tweet));
+    while let Some(source) = current {
+        sources.push(source);
+        current = source.quoted_status.as_deref().map(original_tweet);
+    }
+    sources
+}
+
+pub fn screen_name(tweet: &egg_mode::tweet::Tweet) -> Option<String> {
+    tweet.user.as_ref().map(|user| user.screen_name.clone())
+}
+
+/// Url of the mp4 variant of a gif or video with the highest bitrate.
+fn best_video_variant(entry: &egg_mode::entities::MediaEntity) -> Option<String> {
+    entry
+        .video_info
+        .as_ref()?
+        .variants
+        .iter()
+        .filter(|variant| variant.content_type.essence_str() == "video/mp4")
+        .max_by_key(|variant| variant.bitrate.unwrap_or(0))
+        .map(|variant| variant.url.clone())
+}
+
+/// All media of the requested kinds attached to the given timeline entry, attributed to their
+/// original authors.
+pub fn tweet_media(
+    tweet: &egg_mode::tweet::Tweet,
+    username: &str,
+    kinds: &filter::MediaKinds,
+) -> Vec<Media> {
+    use egg_mode::entities::MediaType;
+
+    let mut result = vec![];
+    for source in media_sources(tweet) {
+        // Only the extended entities list all media of a tweet including their video variants.
+        let media = match (&source.extended_entities, &source.entities.media) {
+            (Some(extended_entities), _) => &extended_entities.media,
+            (None, Some(media)) => media,
+            (None, None) => continue,
+        };
+        for entry in media {
+            let url = match entry.media_type {
+                MediaType::Photo if kinds.photos => {
+                    if entry.expanded_url.contains("/video/") {
+                        // Skip every entry, which expanded_url has a /video/ segment.
+                        // Unfortunately video thumbnails are presented with "media_type" photo :(
+                        continue;
+                    }
+                    entry.media_url.clone()
+                }
+                MediaType::Gif if kinds.gifs => match best_video_variant(entry) {
+                    Some(url) => url,
+                    None => continue,
+                },
+                MediaType::Video if kinds.videos => match best_video_variant(entry) {
+                    Some(url) => url,
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            result.push(Media {
+                url,
+                media_id: Some(entry.id),
+                tweet_id: source.id,
+                author: username.to_string(),
+                original_author: screen_name(source).unwrap_or_else(|| username.to_string()),
+                created_at: Some(source.created_at),
+                text: Some(source.text.clone()),
+                favorite_count: Some(source.favorite_count),
+                retweet_count: Some(source.retweet_count),
+                source: source::Source::Timeline,
+                withheld: withheld::of(source),
+                width: Some(entry.sizes.large.w),
+                height: Some(entry.sizes.large.h),
+            });
+        }
+    }
+    result
+}
+
+/// The media of the given timeline entry, which pass the criteria of the filter concerning single
+/// media.
+fn filtered_media(
+    tweet: &egg_mode::tweet::Tweet,
+    username: &str,
+    filter: &TweetFilter,
+) -> Vec<Media> {
+    if filter.min_photos.is_some() {
+        let photos = tweet_media(tweet, username, &filter::MediaKinds::PHOTOS).len();
+        if filter.check_photo_count(photos) != Verdict::Accept {
+            return vec![];
+        }
+    }
+    tweet_media(tweet, username, &filter.media_kinds)
+        .into_iter()
+        .filter(|media| filter.check_dimensions(media.width, media.height) == Verdict::Accept)
+        .filter(|media| filter.check_format(&media.url) == Verdict::Accept)
+        .collect()
+}
+
+/// Number of times a timeline page is requested again after a transient failure.
+const MAX_PAGE_RETRIES: u32 = 3;
+
+/// Length of a rate limit window of the twitter API.
+const RATE_LIMIT_WINDOW: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// Wait until an exhausted rate limit resets at the given unix time, counting down on the spinner.
+///
+/// Without a known reset time a complete rate limit window is waited for.
+async fn wait_for_rate_limit(spinner: &progress::Spinner, reset: Option<i64>) {
+    let now = chrono::Utc::now().timestamp();
+    // The limit resets within the given second, which is therefore waited for completely.
+    let reset = reset.unwrap_or(now + RATE_LIMIT_WINDOW.as_secs() as i64) + 1;
+    loop {
+        let remaining = reset - chrono::Utc::now().timestamp();
+        if remaining <= 0 || interrupt::requested() {
+            break;
+        }
+        spinner.set_message(format!(
+            "Rate limit reached, continuing in {}:{:02}...",
+            remaining / 60,
+            remaining % 60
+        ));
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+async fn get_urls(
+    tokens: Vec<egg_mode::Token>,
+    username: String,
+    options: TimelineOptions,
+    sources: &[source::Source],
+    filter: &TweetFilter,
+    fixtures: &fixtures::Fixtures,
+    mut collector: collector::Collector,
+) -> Result<(), retrieval::Error> {
+    let mut page: u32 = 0;
+    let mut result = Ok(());
+
+    let spinner = collector.spinner();
+
+    let user_id = egg_mode::user::UserID::ScreenName(username.to_owned().into());
+    let mut tokens = tokens;
+    let mut token_index = 0;
+    let mut rate_limited_tokens = 0;
+    let mut page_retries = 0;
+    let mut token = tokens[token_index].clone();
+
+    if !fixtures.is_replay() {
+        spinner.set_message(format!("Looking up user {}...", username));
+        if let Err(err) = retrieval::check_account(&username, &token).await {
+            spinner.finish_with_message(format!("User {} could not be retrieved", username));
+            collector.finish();
+            return Err(err);
+        }
+    }
+
+    'sources: for &source in sources {
+        let mut timeline = user_timeline(token.clone(), user_id.clone(), options, source);
+        // Likes are ordered by the time of liking, therefore neither tweet ids nor dates bound them.
+        let since_id = filter
+            .since_id
+            .filter(|_| source == source::Source::Timeline);
+        if source == source::Source::Timeline {
+            // The next page is requested below the oldest tweet seen so far.
+            timeline.min_id = filter.max_id.map(|max_id| max_id + 1);
+        }
+
+        'retrieval: loop {
+            if collector.is_full() {
+                break 'sources;
+            }
+            spinner.set_message(format!(
+                "Retrieving {} of user {} ({} tweets / {} images)...",
+                source.name(),
+                username,
+                collector.tweets(),
+                collector.len()
+            ));
+            page += 1;
+            let feed = if fixtures.is_replay() {
+                match fixtures.replay(page) {
+                    Some(feed) => feed,
+                    None => break,
+                }
+            } else {
+                // The timeline is consumed by the request, therefore remember the pagination point
+                // in case the request needs to be resumed with fresh credentials.
+                let (min_id, max_id) = (timeline.min_id, timeline.max_id);
+                let resumed_timeline = |token: &egg_mode::Token| {
+                    let mut timeline =
+                        user_timeline(token.clone(), user_id.clone(), options, source);
+                    timeline.min_id = min_id;
+                    timeline.max_id = max_id;
+                    timeline
+                };
+                debug!(
+                    "Requesting page {} of the {} of user {} (min id {:?}, max id {:?})",
+                    page,
+                    source.name(),
+                    username,
+                    min_id,
+                    max_id
+                );
+                match timeline.older(since_id).await {
+                    Ok((new_timeline, feed)) => {
+                        debug!(
+                            "Received {} tweets, {} of {} requests left until {}",
+                            feed.response.len(),
+                            feed.rate_limit_status.remaining,
+                            feed.rate_limit_status.limit,
+                            feed.rate_limit_status.reset
+                        );
+                        timeline = new_timeline;
+                        rate_limited_tokens = 0;
+                        page_retries = 0;
+                        fixtures.record(page, &feed);
+                        feed.response
+                    }
+                    Err(err) if auth::is_expired(&err) => {
+                        debug!("Access token expired: {}", err);
+                        spinner.disable_steady_tick();
+                        match auth::reauthenticate(&token, &spinner).await {
+                            Some(new_token) => {
+                                token = new_token;
+                                tokens[token_index] = token.clone();
+                                timeline = resumed_timeline(&token);
+                                spinner.enable_steady_tick(80);
+                                page -= 1;
+                                continue;
+                            }
+                            None => {
+                                result = Err(retrieval::Error::Unauthorized);
+                                break 'sources;
+                            }
+                        }
+                    }
+                    Err(err)
+                        if auth::is_rate_limited(&err)
+                            && rate_limited_tokens + 1 < tokens.len() =>
+                    {
+                        debug!(
+                            "Rate limit of API token {} reached: {}",
+                            token_index + 1,
+                            err
+                        );
+                        rate_limited_tokens += 1;
+                        token_index = (token_index + 1) % tokens.len();
+                        token = tokens[token_index].clone();
+                        spinner.println(format!(
+                            "Rate limit reached, switching to API token {} of {}",
+                            token_index + 1,
+                            tokens.len()
+                        ));
+                        timeline = resumed_timeline(&token);
+                        page -= 1;
+                        continue;
+                    }
+                    Err(err) if auth::is_rate_limited(&err) => {
+                        // Every available token is exhausted. The current one was exhausted
+                        // last, so all of them are usable again once its limit resets.
+                        wait_for_rate_limit(&spinner, auth::rate_limit_reset(&err)).await;
+                        if interrupt::requested() {
+                            break 'sources;
+                        }
+                        rate_limited_tokens = 0;
+                        timeline = resumed_timeline(&token);
+                        page -= 1;
+                        continue;
+                    }
+                    Err(err) if auth::is_transient(&err) && page_retries < MAX_PAGE_RETRIES => {
+                        page_retries += 1;
+                        spinner.println(format!(
+                            "Retrieving page {} failed ({}), retrying ({} of {})",
+                            page, err, page_retries, MAX_PAGE_RETRIES
+                        ));
+                        // Back off a little longer with every attempt.
+                        tokio::time::sleep(std::time::Duration::from_secs(1 << page_retries)).await;
+                        if interrupt::requested() {
+                            break 'sources;
+                        }
+                        timeline = resumed_timeline(&token);
+                        page -= 1;
+                        continue;
+                    }
+                    Err(err) => {
+                        debug!("Retrieving page {} failed: {:?}", page, err);
+                        result = Err(retrieval::Error::Api(err));
+                        break 'sources;
+                    }
+                }
+            };
+            events::emit(events::Event::PageFetched {
+                source,
+                page,
+                tweets: feed.len(),
+            });
+
+            for tweet in &feed {
+                if collector.is_full() {
+                    break 'sources;
+                }
+                let verdict = filter.check(tweet);
+                trace!("Tweet {}: {:?}", tweet.id, verdict);
+                match verdict {
+                    Verdict::Accept => {}
+                    Verdict::Skip => {
+                        collector.scanned();
+                        continue;
+                    }
+                    Verdict::Stop if source == source::Source::Timeline => break 'retrieval,
+                    Verdict::Stop => {
+                        collector.scanned();
+                        continue;
+                    }
+                }
+                let media = filtered_media(tweet, &username, filter);
+                if !media.is_empty() {
+                    collector.dump(tweet);
+                }
+                for mut media in media {
+                    media.source = source;
+                    collector.add(media);
+                    if collector.is_full() {
+                        break 'sources;
+                    }
+                }
+                collector.scanned();
+            }
+
+            if !fixtures.is_replay() && timeline.min_id.is_none() {
+                // We are looping the tweet cycle
+                break;
+            }
+        }
+    }
+
+    spinner.finish_with_message(format!(
+        "Tweets for user {} retrieved ({} tweets / {} images)...",
+        username,
+        collector.tweets(),
+        collector.len()
+    ));
+
+    collector.finish();
+    result
+}
+
+/// Where the tweets of a timeline are retrieved from.
+pub enum Backend {
+    /// The official API, switching between the given credentials once a rate limit is reached.
+    Api {
+        tokens: Vec<egg_mode::Token>,
+        fixtures: fixtures::Fixtures,
+    },
+    /// The media tab of the Nitter instance at the given url.
+    Nitter {
+        client: reqwest::Client,
+        instance: String,
+    },
+    /// The web API of twitter.com, authenticated with the cookies of a browser session.
+    Web { client: reqwest::Client },
+}
+
+/// Retrieves the timeline of a user and passes the media of every tweet, which passes the filter,
+/// on to a `Collector`.
+pub struct MediaCollector<'a> {
+    pub backend: Backend,
+    /// Cookies sent with the requests of the Nitter and web backends.
+    pub cookie_jar: &'a cookies::CookieJar,
+    pub username: String,
+    pub options: TimelineOptions,
+    /// Sources to retrieve, in order. Only the API supports sources other than the timeline.
+    pub sources: Vec<source::Source>,
+    pub filter: &'a TweetFilter,
+}
+
+impl MediaCollector<'_> {
+    /// Retrieve the timeline until it is exhausted, the collector is full or the run is
+    /// interrupted.
+    pub async fn collect(self, collector: collector::Collector) -> Result<(), retrieval::Error> {
+        match self.backend {
+            Backend::Api { tokens, fixtures } => {
+                get_urls(
+                    tokens,
+                    self.username,
+                    self.options,
+                    &self.sources,
+                    self.filter,
+                    &fixtures,
+                    collector,
+                )
+                .await
+            }
+            Backend::Nitter { client, instance } => {
+                nitter::get_urls(
+                    &client,
+                    self.cookie_jar,
+                    instance,
+                    self.username,
+                    self.options,
+                    self.filter,
+                    collector,
+                )
+                .await
+            }
+            Backend::Web { client } => {
+                web::get_urls(
+                    &client,
+                    self.cookie_jar,
+                    self.username,
+                    self.options,
+                    self.filter,
+                    collector,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Settings controlling how and where images are downloaded.
+pub struct DownloadOptions {
+    pub max_requests: u32,
+    pub target_directory: String,
+    /// Decides about the paths of the downloaded images.
+    pub naming: Box<dyn naming::NamingStrategy>,
+    pub download_window: Option<schedule::DownloadWindow>,
+    pub write_metadata: bool,
+    /// Store the tweet text in a `<image>.txt` file next to every image.
+    pub save_text: bool,
+    pub embed_metadata: bool,
+    /// Set the modification time of each file to the creation time of its tweet.
+    pub tweet_mtime: bool,
+    pub manifest: Option<manifest::Manifest>,
+    /// Log of every processed image in `manifest.ndjson`.
+    pub ndjson_manifest: Option<ndjson::Manifest>,
+    /// Spreadsheet listing the saved file of every image.
+    pub csv_export: Option<csv_export::CsvExport>,
+    /// Directory to store an additional small preview of every image in.
+    pub preview_directory: Option<String>,
+    /// What to do with images, whose target file already exists.
+    pub on_conflict: conflict::Conflict,
+    /// Only skip existing files if their size matches the remote file.
+    pub verify_size: bool,
+    /// Check downloads against the checksum announced by the server.
+    pub verify_checksum: bool,
+    /// Validators of earlier downloads to only download changed files again.
+    pub etag_cache: Option<etag::Cache>,
+    /// Archive to store all downloads in instead of individual files.
+    pub archive: Option<archive::Archive>,
+    /// Time after which a download is deferred to the slow queue.
+    pub slow_item_budget: Option<std::time::Duration>,
+    /// Time after which a download is given up on and reported as failed.
+    pub per_file_timeout: Option<std::time::Duration>,
+    /// Bounds of the file size in bytes, files outside of them are not stored.
+    pub min_file_size: Option<u64>,
+    pub max_file_size: Option<u64>,
+    /// Image formats to store (eg. `jpg`), all if empty.
+    pub formats: Vec<String>,
+    /// Remote storage to upload all downloads to instead of the target directory.
+    pub remote: Option<remote::Remote>,
+    /// Client to retry the media of withheld tweets with.
+    pub withheld_client: Option<reqwest::Client>,
+    /// Limit of the requests started per second.
+    pub requests_per_second: Option<f64>,
+    /// Maximum time to wait for data from a server, before the download is considered failed.
+    pub read_timeout: Option<std::time::Duration>,
+}
+
+/// The slow queue is processed with this fraction of the concurrent requests.
+const SLOW_QUEUE_DIVISOR: usize = 4;
+
+/// Wait for the next part of a response from the given url, and return a failed request or an
+/// exceeded read timeout as error instead of giving up on the whole run.
+async fn try_read<T>(
+    url: &str,
+    read_timeout: Option<std::time::Duration>,
+    future: impl std::future::Future<Output = reqwest::Result<T>>,
+) -> Result<T, String> {
+    let result = match read_timeout {
+        Some(read_timeout) => tokio::time::timeout(read_timeout, future)
+            .await
+            .map_err(|_| format!("Timed out waiting for data from {}", url))?,
+        None => future.await,
+    };
+    result.map_err(|err| format!("Could not download url {}: {}", url, err))
+}
+
+async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    read_timeout: Option<std::time::Duration>,
+) -> Result<bytes::Bytes, String> {
+    let mut response = try_read(url, read_timeout, client.get(url).send()).await?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Could not download url {}: {}",
+            url,
+            response.status()
+        ));
+    }
+    let mut bytes = vec![];
+    while let Some(chunk) = try_read(url, read_timeout, response.chunk()).await? {
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes.into())
+}
+
+/// Outcome of a successful download.
+enum Fetched {
+    /// The complete content of the file, together with its Content-Type if known.
+    Complete(bytes::Bytes, Option<String>),
+    /// The file did not change since it was downloaded the last time.
+    NotModified,
+}
+
+/// Download the given url into a partial file, resuming an earlier interrupted download of it with
+/// a range request.
+///
+/// The size of the complete file is checked against the size announced by the server, and if
+/// requested also against the checksum announced by it. A mismatch is returned as error, just like
+/// a failed request. The partial file is removed once the download is complete.
+///
+/// With an ETag cache the validators of the download are recorded, and for a conditional download
+/// the file is only transferred if it changed since the recorded download.
+async fn fetch_resumable(
+    client: &reqwest::Client,
+    url: &str,
+    read_timeout: Option<std::time::Duration>,
+    part_path: &std::path::Path,
+    verify_checksum: bool,
+    etag_cache: Option<&etag::Cache>,
+    conditional: bool,
+) -> Result<Fetched, String> {
+    use reqwest::{header, StatusCode};
+
+    let mut offset = tokio::fs::metadata(part_path)
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    let (mut response, mut file, expected_size, checksum) = loop {
+        let mut request = client.get(url);
+        if offset > 0 {
+            request = request.header(header::RANGE, format!("bytes={}-", offset));
+        } else if let Some(validators) = etag_cache
+            .filter(|_| conditional)
+            .and_then(|etag_cache| etag_cache.get(url))
+        {
+            request = validators.apply(request);
+        }
+        debug!(
+            "Requesting {} (offset {}, conditional {})",
+            url, offset, conditional
+        );
+        let response = try_read(url, read_timeout, request.send()).await?;
+        trace!(
+            "Response to {}: {} {:?}",
+            url,
+            response.status(),
+            response.headers()
+        );
+        match response.status() {
+            StatusCode::NOT_MODIFIED if conditional => {
+                debug!("{} is not modified", url);
+                return Ok(Fetched::NotModified);
+            }
+            StatusCode::PARTIAL_CONTENT => {
+                // Content-Range: bytes <start>-<end>/<size>
+                let size = response
+                    .headers()
+                    .get(header::CONTENT_RANGE)
+                    .and_then(|range| range.to_str().ok())
+                    .and_then(|range| range.rsplit('/').next())
+                    .and_then(|size| size.parse::<u64>().ok());
+                let checksum = checksum::Checksum::announced(response.headers(), true);
+                let file = tokio::fs::OpenOptions::new()
+                    .append(true)
+                    .open(part_path)
+                    .await
+                    .map_err(|err| {
+                        format!("Could not open file for writing {:?}: {}", part_path, err)
+                    })?;
+                break (response, file, size, checksum);
+            }
+            // The partial file does not match the remote file anymore, start over.
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => offset = 0,
+            status if !status.is_success() => {
+                return Err(format!("Could not download url {}: {}", url, status));
+            }
+            _ => {
+                let size = response.content_length();
+                let checksum = checksum::Checksum::announced(response.headers(), false);
+                let file = tokio::fs::File::create(part_path).await.map_err(|err| {
+                    format!("Could not open file for writing {:?}: {}", part_path, err)
+                })?;
+                break (response, file, size, checksum);
+            }
+        }
+    };
+    let validators = etag::Validators::of(response.headers());
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_string());
+
+    let write_failed =
+        |err: std::io::Error| format!("Could not write file {:?}: {}", part_path, err);
+    while let Some(chunk) = try_read(url, read_timeout, response.chunk()).await? {
+        file.write_all(&chunk).await.map_err(write_failed)?;
+    }
+    file.flush().await.map_err(write_failed)?;
+    drop(file);
+
+    let bytes = tokio::fs::read(part_path)
+        .await
+        .map_err(|err| format!("Could not read file {:?}: {}", part_path, err))?;
+    if let Some(expected_size) = expected_size {
+        if bytes.len() as u64 != expected_size {
+            if bytes.len() as u64 > expected_size {
+                // Resuming can not repair a partial file, which does not belong to the remote file.
+                let _ = tokio::fs::remove_file(part_path).await;
+            }
+            return Err(format!(
+                "Incomplete download of {} ({} of {} bytes)",
+                url,
+                bytes.len(),
+                expected_size
+            ));
+        }
+    }
+    let _ = tokio::fs::remove_file(part_path).await;
+    if let Some(checksum) = checksum.filter(|_| verify_checksum) {
+        if !checksum.matches(&bytes) {
+            return Err(format!(
+                "Corrupt download of {} (expected checksum {})",
+                url, checksum
+            ));
+        }
+    }
+    if let (Some(etag_cache), Some(validators)) = (etag_cache, validators) {
+        etag_cache.set(url, &validators);
+    }
+    debug!("Downloaded {} ({} bytes)", url, bytes.len());
+    Ok(Fetched::Complete(bytes.into(), content_type))
+}
+
+/// Size of the given remote file as announced by the server.
+async fn remote_size(
+    client: &reqwest::Client,
+    url: &str,
+    read_timeout: Option<std::time::Duration>,
+) -> Option<u64> {
+    let response = try_read(url, read_timeout, client.head(url).send())
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Write a download to the given partial file and move it into place once it is complete.
+///
+/// The data is synced to disk before the rename, so an interrupted run never leaves a truncated
+/// file under the final name.
+async fn store(
+    part_path: &std::path::Path,
+    path: &str,
+    bytes: &[u8],
+    modified: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<(), String> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|err| format!("Could not create directory for {}: {}", path, err))?;
+    }
+    let write_failed =
+        |err: std::io::Error| format!("Could not write file {:?}: {}", part_path, err);
+    let mut f = tokio::fs::File::create(part_path)
+        .await
+        .map_err(|err| format!("Could not open file for writing {:?}: {}", part_path, err))?;
+    f.write_all(bytes).await.map_err(write_failed)?;
+    let f = f.into_std().await;
+    if let Some(modified) = modified {
+        f.set_modified(modified.into()).map_err(|err| {
+            format!(
+                "Could not set modification time of {:?}: {}",
+                part_path, err
+            )
+        })?;
+    }
+    f.sync_all().map_err(write_failed)?;
+    drop(f);
+    tokio::fs::rename(part_path, path)
+        .await
+        .map_err(|err| format!("Could not move download to {}: {}", path, err))
+}
+
+/// Download the given images, identified by their position in the queue, as soon as they are
+/// found.
+async fn download_urls(
+    client: &reqwest::Client,
+    urls: impl futures::Stream<Item = (usize, Media)>,
+    progress: &dyn progress::DownloadProgress,
+    queue: &queue::Queue,
+    options: DownloadOptions,
+) -> Result<(u64, summary::Downloads), DownloadError> {
+    let DownloadOptions {
+        max_requests,
+        target_directory,
+        naming,
+        download_window,
+        write_metadata,
+        save_text,
+        embed_metadata,
+        tweet_mtime,
+        manifest,
+        ndjson_manifest,
+        csv_export,
+        preview_directory,
+        on_conflict,
+        verify_size,
+        verify_checksum,
+        etag_cache,
+        archive,
+        remote,
+        slow_item_budget,
+        per_file_timeout,
+        min_file_size,
+        max_file_size,
+        formats,
+        withheld_client,
+        requests_per_second,
+        read_timeout,
+    } = options;
+    let throttle = requests_per_second.map(throttle::Throttle::new);
+    let staging = staging::Staging::create(std::path::Path::new(&target_directory));
+
+    let claimed_paths: std::sync::Mutex<std::collections::HashSet<String>> = Default::default();
+    let failures = failed::Failures::default();
+    let downloads: std::sync::Mutex<summary::Downloads> = Default::default();
+    let stop_error: std::sync::Mutex<Option<DownloadError>> = Default::default();
+    // Downloads exceeding the budget are cancelled and returned to be retried later, as are
+    // incomplete or corrupt downloads unless it is the last attempt.
+    let download = |index: usize,
+                    media: Media,
+                    budget: Option<std::time::Duration>,
+                    last_attempt: bool| {
+        let target_directory = &target_directory;
+        let naming = &naming;
+        let staging = &staging;
+        let manifest = &manifest;
+        let ndjson_manifest = &ndjson_manifest;
+        let csv_export = &csv_export;
+        let preview_directory = &preview_directory;
+        let archive = &archive;
+        let remote = &remote;
+        let claimed_paths = &claimed_paths;
+        let withheld_client = &withheld_client;
+        let throttle = &throttle;
+        let etag_cache = &etag_cache;
+        let failures = &failures;
+        let downloads = &downloads;
+        let formats = &formats;
+        let stop_error = &stop_error;
+        async move {
+            // After a conflict or a failure to store an image no further downloads are
+            // started, they stay in the queue instead.
+            if stop_error.lock().unwrap().is_some() {
+                return Some((index, media));
+            }
+            let url = &match preview_directory {
+                Some(_) => canonical::variant_url(&media.url, "orig"),
+                None => media.url.clone(),
+            };
+            // Report the outcome of the download, with the path the image is stored at.
+            let report = |path: &str, bytes: Option<u64>, status: ndjson::Status| {
+                events::emit(events::Event::FileDone {
+                    url,
+                    path,
+                    status,
+                    bytes,
+                });
+                downloads.lock().unwrap().record(status, bytes);
+                if status != ndjson::Status::Failed {
+                    queue.forget_failure(&media.url);
+                }
+                if let Some(ndjson_manifest) = ndjson_manifest {
+                    ndjson_manifest.append(url, path, media.tweet_id, bytes, status);
+                }
+                if let (Some(csv_export), false) = (
+                    csv_export,
+                    matches!(
+                        status,
+                        ndjson::Status::Withheld
+                            | ndjson::Status::Failed
+                            | ndjson::Status::Filtered
+                    ),
+                ) {
+                    csv_export.add(&media, path);
+                }
+            };
+            let parsed_url = Url::parse(&media.url)
+                .unwrap_or_else(|_| panic!("Could not parse URL: {}", media.url));
+            let file_name = parsed_url
+                .path()
+                .split('/')
+                .next_back()
+                .unwrap_or_else(|| panic!("Could not extract filename from url {}", url));
+            let mut relative_path = naming.relative_path(&media, file_name);
+            if media.source == source::Source::Likes {
+                relative_path = format!("{}/{}", source::LIKES_DIRECTORY, relative_path);
+            }
+
+            if archive.is_none() && remote.is_none() {
+                let original_path = relative_path.clone();
+                let mut number = 0;
+                loop {
+                    let path = format!("{}/{}", target_directory, relative_path);
+                    // Another download of this run may already have chosen the same name.
+                    let claimed = !claimed_paths.lock().unwrap().insert(relative_path.clone());
+                    let existing = tokio::fs::metadata(&path).await.ok();
+                    if !claimed && existing.is_none() {
+                        break;
+                    }
+                    let same_download = || {
+                        conflict::is_same_download(
+                            target_directory,
+                            &relative_path,
+                            &media.url,
+                            url,
+                            manifest.as_ref(),
+                        )
+                    };
+                    match on_conflict {
+                        conflict::Conflict::Overwrite => break,
+                        conflict::Conflict::Skip => {
+                            let unchanged = match &existing {
+                                Some(metadata) if verify_size => {
+                                    if let Some(throttle) = throttle {
+                                        throttle.wait().await;
+                                    }
+                                    remote_size(client, url, read_timeout).await
+                                        == Some(metadata.len())
+                                }
+                                // Leave the decision to a conditional download.
+                                Some(_)
+                                    if etag_cache
+                                        .as_ref()
+                                        .and_then(|etag_cache| etag_cache.get(url))
+                                        .is_some() =>
+                                {
+                                    false
+                                }
+                                _ => true,
+                            };
+                            if !unchanged {
+                                break;
+                            }
+                        }
+                        conflict::Conflict::Rename if claimed || !same_download() => {
+                            number += 1;
+                            relative_path = conflict::numbered(&original_path, number);
+                            continue;
+                        }
+                        conflict::Conflict::Error if claimed || !same_download() => {
+                            if !claimed {
+                                claimed_paths.lock().unwrap().remove(&relative_path);
+                            }
+                            stop_error
+                                .lock()
+                                .unwrap()
+                                .get_or_insert(DownloadError::Conflict {
+                                    url: media.url.clone(),
+                                    path,
+                                });
+                            return Some((index, media));
+                        }
+                        conflict::Conflict::Rename | conflict::Conflict::Error => {}
+                    }
+                    report(
+                        &relative_path,
+                        existing.map(|metadata| metadata.len()),
+                        ndjson::Status::Skipped,
+                    );
+                    progress.processed();
+                    return None;
+                }
+            }
+            let path = format!("{}/{}", target_directory, relative_path);
+
+            if let Some(window) = download_window {
+                if window.time_until_open().is_some() {
+                    progress.status(index, format!("Waiting for download window {}", window));
+                    window.wait().await;
+                    // The download stays in the queue.
+                    if interrupt::requested() {
+                        claimed_paths.lock().unwrap().remove(&relative_path);
+                        return Some((index, media));
+                    }
+                }
+            }
+            let outside_size_bounds = |size: u64| {
+                min_file_size.is_some_and(|min| size < min)
+                    || max_file_size.is_some_and(|max| size > max)
+            };
+            // Files outside the size bounds are sorted out before downloading them, as far as
+            // the server announces their size.
+            let announced_size = if min_file_size.is_some() || max_file_size.is_some() {
+                if let Some(throttle) = throttle {
+                    throttle.wait().await;
+                }
+                progress.status(index, format!("Checking size: {}", url));
+                remote_size(client, url, read_timeout).await
+            } else {
+                None
+            };
+            if let Some(size) = announced_size.filter(|size| outside_size_bounds(*size)) {
+                claimed_paths.lock().unwrap().remove(&relative_path);
+                report(&relative_path, Some(size), ndjson::Status::Filtered);
+                progress.processed();
+                return None;
+            }
+            if let Some(throttle) = throttle {
+                throttle.wait().await;
+            }
+            // An existing file is only replaced if it changed since it was downloaded.
+            let existing = match (archive, remote) {
+                (None, None) => tokio::fs::metadata(&path).await.ok(),
+                _ => None,
+            };
+            progress.status(index, format!("Downloading: {}", url));
+            events::emit(events::Event::FileStarted { url });
+            let fetched = async {
+                match &media.withheld {
+                    // The CDN may refuse to deliver withheld media, which must not end up as file.
+                    Some(_) => withheld::fetch(client, withheld_client.as_ref(), url, read_timeout)
+                        .await
+                        .map(|bytes| Ok(Fetched::Complete(bytes, None))),
+                    None => Some(
+                        fetch_resumable(
+                            client,
+                            url,
+                            read_timeout,
+                            &staging.resume_path(url),
+                            verify_checksum,
+                            etag_cache.as_ref(),
+                            existing.is_some(),
+                        )
+                        .await,
+                    ),
+                }
+            };
+            // A download exceeding the per file timeout is given up on for good.
+            let fetched = async {
+                match per_file_timeout {
+                    Some(limit) => tokio::time::timeout(limit, fetched).await.map_err(|_| {
+                        format!("Gave up on {} after {} seconds", url, limit.as_secs())
+                    }),
+                    None => Ok(fetched.await),
+                }
+            };
+            let fetched = match budget {
+                Some(budget) => match tokio::time::timeout(budget, fetched).await {
+                    Ok(fetched) => fetched,
+                    Err(_) => {
+                        progress.status(index, format!("Deferring slow download: {}", url));
+                        claimed_paths.lock().unwrap().remove(&relative_path);
+                        return Some((index, media));
+                    }
+                },
+                None => fetched.await,
+            };
+            let (mut bytes, content_type) = match fetched {
+                Ok(Some(Ok(Fetched::Complete(bytes, content_type)))) => (bytes, content_type),
+                Ok(Some(Ok(Fetched::NotModified))) => {
+                    report(
+                        &relative_path,
+                        existing.map(|metadata| metadata.len()),
+                        ndjson::Status::Skipped,
+                    );
+                    progress.processed();
+                    return None;
+                }
+                Ok(Some(Err(reason))) if !last_attempt => {
+                    progress.message(index, &format!("{}, trying again later", reason));
+                    claimed_paths.lock().unwrap().remove(&relative_path);
+                    return Some((index, media));
+                }
+                Ok(Some(Err(reason))) | Err(reason) => {
+                    progress.message(index, &reason);
+                    events::emit(events::Event::Error {
+                        url: Some(url),
+                        message: &reason,
+                    });
+                    claimed_paths.lock().unwrap().remove(&relative_path);
+                    failures.add(&media.url, media.tweet_id, &relative_path, &reason);
+                    queue.record_failure(&media.url, &relative_path, &media);
+                    report(&relative_path, None, ndjson::Status::Failed);
+                    progress.processed();
+                    return None;
+                }
+                Ok(None) => {
+                    progress.message(
+                        index,
+                        &format!(
+                            "Could not download {} of tweet {}, which is {}",
+                            url,
+                            media.tweet_id,
+                            media.withheld.as_ref().unwrap()
+                        ),
+                    );
+                    claimed_paths.lock().unwrap().remove(&relative_path);
+                    report(&relative_path, None, ndjson::Status::Withheld);
+                    progress.processed();
+                    return None;
+                }
+            };
+            // Urls without a recognizable format are judged by the format actually served.
+            let unwanted_format = !formats.is_empty()
+                && filter::url_format(url).is_none()
+                && content_type
+                    .as_deref()
+                    .and_then(filter::content_type_format)
+                    .is_some_and(|format| !formats.contains(&format));
+            if unwanted_format
+                || (announced_size.is_none() && outside_size_bounds(bytes.len() as u64))
+            {
+                claimed_paths.lock().unwrap().remove(&relative_path);
+                report(
+                    &relative_path,
+                    Some(bytes.len() as u64),
+                    ndjson::Status::Filtered,
+                );
+                progress.processed();
+                return None;
+            }
+            // Hash the content as served, so embedded metadata does not hide duplicates.
+            let hash = manifest::hash(&bytes);
+            if let Some(manifest) = manifest {
+                if let Some(existing) = manifest.path_by_hash(&hash) {
+                    manifest.record(media.tweet_id, url, &existing, &hash);
+                    report(
+                        &existing,
+                        Some(bytes.len() as u64),
+                        ndjson::Status::Duplicate,
+                    );
+                    progress.processed();
+                    return None;
+                }
+            }
+            if embed_metadata {
+                bytes = embed::embed(bytes, &media);
+            }
+            let modified = media.created_at.filter(|_| tweet_mtime);
+            if let Some(archive) = archive {
+                archive.add(&relative_path, &bytes, modified);
+                if write_metadata {
+                    let sidecar_path = format!("{}.json", relative_path);
+                    archive.add(&sidecar_path, &sidecar::render(&media), None);
+                }
+                if let Some(text) = sidecar::render_text(&media).filter(|_| save_text) {
+                    archive.add(&format!("{}.txt", relative_path), &text, None);
+                }
+            } else if let Some(remote) = remote {
+                progress.status(index, format!("Uploading: {}", relative_path));
+                let uploaded = async {
+                    remote.put(client, &relative_path, &bytes).await?;
+                    if write_metadata {
+                        let sidecar_key = format!("{}.json", relative_path);
+                        remote
+                            .put(client, &sidecar_key, &sidecar::render(&media))
+                            .await?;
+                    }
+                    if let Some(text) = sidecar::render_text(&media).filter(|_| save_text) {
+                        let text_key = format!("{}.txt", relative_path);
+                        remote.put(client, &text_key, &text).await?;
+                    }
+                    Ok::<(), String>(())
+                };
+                if let Err(reason) = uploaded.await {
+                    progress.message(index, &reason);
+                    events::emit(events::Event::Error {
+                        url: Some(url),
+                        message: &reason,
+                    });
+                    claimed_paths.lock().unwrap().remove(&relative_path);
+                    failures.add(&media.url, media.tweet_id, &relative_path, &reason);
+                    queue.record_failure(&media.url, &relative_path, &media);
+                    report(&relative_path, None, ndjson::Status::Failed);
+                    progress.processed();
+                    return None;
+                }
+            } else {
+                let stored = async {
+                    store(&staging.part_path(index), &path, &bytes, modified).await?;
+                    if write_metadata {
+                        sidecar::write(std::path::Path::new(&path), &media).await?;
+                    }
+                    if save_text {
+                        sidecar::write_text(std::path::Path::new(&path), &media).await?;
+                    }
+                    Ok::<(), String>(())
+                };
+                if let Err(message) = stored.await {
+                    claimed_paths.lock().unwrap().remove(&relative_path);
+                    stop_error
+                        .lock()
+                        .unwrap()
+                        .get_or_insert(DownloadError::Io(message));
+                    return Some((index, media));
+                }
+            }
+            if let Some(manifest) = manifest {
+                manifest.record(media.tweet_id, url, &relative_path, &hash);
+            }
+            report(
+                &relative_path,
+                Some(bytes.len() as u64),
+                ndjson::Status::Downloaded,
+            );
+            if let Some(preview_directory) = preview_directory {
+                let preview_url = canonical::variant_url(&media.url, "small");
+                if let Some(throttle) = throttle {
+                    throttle.wait().await;
+                }
+                progress.status(index, format!("Downloading: {}", preview_url));
+                match fetch(client, &preview_url, read_timeout).await {
+                    Ok(mut preview) => {
+                        if embed_metadata {
+                            preview = embed::embed(preview, &media);
+                        }
+                        // The preview tree may live on a different filesystem, which rules out
+                        // the staging directory.
+                        let preview_path = format!("{}/{}", preview_directory, relative_path);
+                        let part_path = format!("{}.part", preview_path);
+                        let stored = store(
+                            std::path::Path::new(&part_path),
+                            &preview_path,
+                            &preview,
+                            modified,
+                        )
+                        .await;
+                        if let Err(reason) = stored {
+                            progress.message(index, &reason);
+                        }
+                    }
+                    // The image itself is stored, which leaves nothing to retry.
+                    Err(reason) => progress.message(index, &reason),
+                }
+            }
+            progress.processed();
+            None
+        }
+    };
+    // Every download, which is not deferred, is done and leaves the persistent queue.
+    let attempt =
+        |index: usize, media: Media, budget: Option<std::time::Duration>, last_attempt: bool| {
+            let download = &download;
+            async move {
+                let deferred = download(index, media, budget, last_attempt).await;
+                if deferred.is_none() {
+                    queue.done(index);
+                }
+                deferred
+            }
+        };
+
+    // After an interruption no further downloads are started, they stay in the queue instead.
+    let deferred: Vec<(usize, Media)> = urls
+        .take_while(|_| futures::future::ready(!interrupt::requested()))
+        .map(|(index, media)| {
+            progress.queued();
+            heap_stats::download_queued();
+            attempt(index, media, slow_item_budget, false)
+        })
+        .buffer_unordered(max_requests as usize)
+        .filter_map(|deferred| async move {
+            if deferred.is_none() {
+                heap_stats::download_finished();
+            }
+            deferred
+        })
+        .collect()
+        .await;
+
+    // Slow and failed downloads are completed with fewer concurrent requests, after all others are
+    // done.
+    if !deferred.is_empty() && !interrupt::requested() && stop_error.lock().unwrap().is_none() {
+        let slow_requests = (max_requests as usize / SLOW_QUEUE_DIVISOR).max(1);
+        futures::stream::iter(
+            deferred
+                .into_iter()
+                .map(|(index, media)| attempt(index, media, None, true)),
+        )
+        .buffer_unordered(slow_requests)
+        .inspect(|_| heap_stats::download_finished())
+        .collect::<Vec<_>>()
+        .await;
+    }
+
+    progress.finish();
+
+    staging.finish();
+    if let Some(archive) = archive {
+        archive.finish();
+    }
+    if let Some(csv_export) = csv_export {
+        csv_export.finish();
+    }
+    failures.write(std::path::Path::new(&target_directory));
+
+    match stop_error.into_inner().unwrap() {
+        Some(err) => Err(err),
+        None => {
+            let downloads = downloads.into_inner().unwrap();
+            Ok((downloads.processed() - downloads.failed, downloads))
+        }
+    }
+}
+
+/// Reason, why the downloads ended before all images were processed.
+#[derive(Debug)]
+pub enum DownloadError {
+    /// The file an image is to be stored at already exists, and conflicts are configured to be an
+    /// error.
+    Conflict { url: String, path: String },
+    /// An image could not be stored, eg. because the disk is full.
+    Io(String),
+}
+
+impl std::fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadError::Conflict { url, path } => {
+                write!(f, "Could not store {}: {} already exists", url, path)
+            }
+            DownloadError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Downloads the images found by a `MediaCollector`.
+pub struct Downloader<'a> {
+    client: &'a reqwest::Client,
+    queue: &'a queue::Queue,
+    options: DownloadOptions,
+}
+
+impl<'a> Downloader<'a> {
+    /// Create a downloader, which removes every image from the given queue once it is done.
+    pub fn new(
+        client: &'a reqwest::Client,
+        queue: &'a queue::Queue,
+        options: DownloadOptions,
+    ) -> Downloader<'a> {
+        Downloader {
+            client,
+            queue,
+            options,
+        }
+    }
+
+    /// Download the given images, identified by their position in the queue, as soon as they
+    /// are found, reporting the progress to the given receiver.
+    ///
+    /// Returns the number of images, which did not fail, together with the outcome of all. Images,
+    /// which were not processed because of an error, stay in the queue.
+    pub async fn download(
+        self,
+        urls: impl futures::Stream<Item = (usize, Media)>,
+        progress: &dyn progress::DownloadProgress,
+    ) -> Result<(u64, summary::Downloads), DownloadError> {
+        download_urls(self.client, urls, progress, self.queue, self.options).await
+    }
+}
+
+/// Create or truncate a file, which holds credentials and therefore is only readable by the current
+/// user. The permissions of an existing file are tightened as well.
+pub fn create_private_file(path: &std::path::Path) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)
+}
+
+/// User-Agent header identifying this tool, unless `--user-agent` is given.
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Keep connections of the given http client open and reusable across requests, so a large job
+/// does not pay for a new TCP and TLS handshake with every single media file.
+pub fn pooled(builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+    builder
+        .pool_idle_timeout(std::time::Duration::from_secs(90))
+        .tcp_keepalive(std::time::Duration::from_secs(60))
+}
+
+/// Build a http client, which routes its requests through a proxy and/or binds them to a local
+/// address, so API requests and media downloads can take different ways to the internet.
+pub fn transport(
+    builder: reqwest::ClientBuilder,
+    proxy: Option<&str>,
+    local_address: Option<&str>,
+) -> Result<reqwest::Client, String> {
+    let mut builder = builder;
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(
+            reqwest::Proxy::all(proxy)
+                .map_err(|err| format!("Invalid proxy {}: {}", proxy, err))?,
+        );
+    }
+    if let Some(local_address) = local_address {
+        builder = builder.local_address(
+            local_address
+                .parse::<std::net::IpAddr>()
+                .map_err(|_| format!("Invalid local address {}", local_address))?,
+        );
+    }
+    builder
+        .build()
+        .map_err(|err| format!("Could not initialize http client: {}", err))
+}
@@ -0,0 +1,75 @@
+use crate::exit_code;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
+use twitter_image_downloader::run;
+
+/// Directives added to every filter: the root span carrying the run id, and the statistics
+/// explicitly requested with `--heap-stats`, are never filtered out.
+const ALWAYS_ENABLED: [&str; 2] = [
+    "twitter_image_downloader::logging=info",
+    "twitter_image_downloader::heap_stats=info",
+];
+
+fn always_enabled(filter: EnvFilter) -> EnvFilter {
+    ALWAYS_ENABLED.iter().fold(filter, |filter, directive| {
+        filter.add_directive(directive.parse().unwrap())
+    })
+}
+
+/// Log events of the downloader itself at the given verbosity (`-v`, `-vv`).
+fn default_filter(verbosity: u64) -> &'static str {
+    match verbosity {
+        0 => "warn",
+        1 => "warn,twitter_image_downloader=debug",
+        _ => "warn,twitter_image_downloader=trace",
+    }
+}
+
+/// The log file receives at least every event of the run, eg. each processed and failed url.
+fn file_filter(verbosity: u64) -> &'static str {
+    match verbosity {
+        0 => "warn,twitter_image_downloader=info",
+        verbosity => default_filter(verbosity),
+    }
+}
+
+/// Print log events to stderr, filtered by `RUST_LOG` if set and by the verbosity otherwise.
+///
+/// If a log file is given, timestamped events are appended to it as well, regardless of what is
+/// displayed on the terminal.
+///
+/// Every event is logged inside a root span carrying the run id, which stays entered as long as
+/// the returned guard is kept.
+pub fn init(verbosity: u64, log_file: Option<&Path>) -> tracing::span::EnteredSpan {
+    let filter = always_enabled(
+        EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new(default_filter(verbosity))),
+    );
+    let terminal = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(console::colors_enabled_stderr())
+        .with_filter(filter);
+
+    let file = log_file.map(|path| {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|err| {
+                eprintln!("Could not open log file {:?}: {}", path, err);
+                std::process::exit(exit_code::IO_ERROR);
+            });
+        tracing_subscriber::fmt::layer()
+            .with_writer(Mutex::new(file))
+            .with_ansi(false)
+            .with_filter(always_enabled(EnvFilter::new(file_filter(verbosity))))
+    });
+
+    tracing_subscriber::registry()
+        .with(terminal)
+        .with(file)
+        .init();
+    tracing::info_span!("run", id = run::id()).entered()
+}
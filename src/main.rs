@@ -1,297 +1,1871 @@
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches, Shell, SubCommand};
 use futures::stream::StreamExt;
-use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
-use tokio::io::AsyncWriteExt;
-use url::Url;
-
-fn access_token(
-    consumer_key: String,
-    consumer_secret: String,
-    access_token: String,
-    access_token_secret: String,
-) -> egg_mode::Token {
-    let api_token = egg_mode::KeyPair::new(consumer_key, consumer_secret);
-    let access_token = egg_mode::KeyPair::new(access_token, access_token_secret);
-
-    egg_mode::Token::Access {
-        consumer: api_token,
-        access: access_token,
-    }
-}
+use twitter_image_downloader::filter::TweetFilter;
+use twitter_image_downloader::{
+    access_token, pooled, screen_name, transport, Backend, DownloadOptions, Downloader, Media,
+    MediaCollector, TimelineOptions, DEFAULT_USER_AGENT,
+};
+use twitter_image_downloader::{
+    archive, auth, browser, collector, conflict, cookies, csv_export, dump, etag, events, failed,
+    filter, fixtures, heap_stats, interrupt, manifest, naming, ndjson, netrc, progress, queue,
+    remote, run, schedule, source, summary, web,
+};
+mod adopt;
+mod browse;
+mod config;
+mod discover;
+mod exit_code;
+mod explain;
+mod gallery;
+mod init;
+mod logging;
+mod manpage;
+mod state;
+mod status;
+mod tags;
+mod terminal;
+mod verify_remote;
 
-fn user_timeline(
-    token: egg_mode::Token,
-    user_id: egg_mode::user::UserID,
-) -> egg_mode::tweet::Timeline {
-    egg_mode::tweet::user_timeline(user_id, false, false, &token)
+// Counting every allocation has a cost, which only builds with the heap-stats feature pay.
+#[cfg(feature = "heap-stats")]
+#[global_allocator]
+static GLOBAL: heap_stats::CountingAllocator = heap_stats::CountingAllocator;
+
+fn validate_proxy(proxy: String) -> Result<(), String> {
+    reqwest::Proxy::all(&proxy)
+        .map(|_| ())
+        .map_err(|_| format!("Invalid proxy url: {}", proxy))
 }
 
-async fn get_urls(token: egg_mode::Token, username: String, max_image_count: u32) -> Vec<String> {
-    let mut tweets_retrieved: u32 = 0;
-    let mut urls: Vec<String> = vec![];
+fn validate_count(count: String) -> Result<(), String> {
+    count
+        .parse::<i32>()
+        .ok()
+        .filter(|count| *count >= 0)
+        .map(|_| ())
+        .ok_or_else(|| format!("Invalid count: {}", count))
+}
 
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_draw_target(ProgressDrawTarget::stdout());
-    spinner.enable_steady_tick(80);
+fn validate_regex(pattern: String) -> Result<(), String> {
+    regex::Regex::new(&pattern)
+        .map(|_| ())
+        .map_err(|err| format!("Invalid regular expression: {}", err))
+}
 
-    let user_id = egg_mode::user::UserID::ScreenName(username.to_owned().into());
-    let mut timeline = user_timeline(token, user_id).with_page_size(200);
+fn validate_tweet_id(id: String) -> Result<(), String> {
+    id.parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| format!("Invalid tweet id: {}", id))
+}
 
-    'retrieval: loop {
-        spinner.set_message(format!(
-            "Retrieving tweets for user {} ({} tweets / {} images)...",
-            username,
-            tweets_retrieved,
-            urls.len()
-        ));
-        match timeline.older(None).await {
-            Ok((new_timeline, feed)) => {
-                timeline = new_timeline;
-                for tweet in &*feed {
-                    if let Some(media) = &tweet.entities.media {
-                        for entry in media {
-                            if entry.media_type != egg_mode::entities::MediaType::Photo {
-                                continue;
-                            }
-
-                            if entry.expanded_url.contains("/video/") {
-                                // Skip every entry, which expanded_url has a /video/ segment.
-                                // Unfortunately video thumbnails are presented with "media_type" photo :(
-                                continue;
-                            }
-
-                            let url = entry.media_url.clone();
-                            urls.push(url);
-                            if max_image_count > 0 && urls.len() >= max_image_count as usize {
-                                break 'retrieval;
-                            }
-                        }
-                    }
-                    tweets_retrieved += 1;
-                }
+/// Parse a file size like `500`, `50K`, `10M` or `1G` into bytes.
+fn parse_file_size(size: &str) -> Result<u64, String> {
+    let (amount, factor) = match size.trim().to_ascii_uppercase() {
+        size if size.ends_with('K') => (size[..size.len() - 1].to_string(), 1 << 10),
+        size if size.ends_with('M') => (size[..size.len() - 1].to_string(), 1 << 20),
+        size if size.ends_with('G') => (size[..size.len() - 1].to_string(), 1 << 30),
+        size => (size, 1),
+    };
+    let amount = amount
+        .parse::<u64>()
+        .map_err(|_| format!("Invalid file size '{}' (eg. 500, 50K, 10M or 1G)", size))?;
+    amount
+        .checked_mul(factor)
+        .ok_or_else(|| format!("File size '{}' is too large", size))
+}
 
-                if let None = timeline.min_id {
-                    // We are looping the tweet cycle
-                    break;
-                }
-            }
-            Err(_err) => {
-                break;
-            }
-        }
+fn validate_timeout(seconds: String) -> Result<(), String> {
+    match seconds.parse::<u64>() {
+        Ok(seconds) if seconds > 0 => Ok(()),
+        _ => Err(format!(
+            "Timeout must be a positive number of seconds: {}",
+            seconds
+        )),
     }
+}
 
-    spinner.finish_with_message(format!(
-        "Tweets for user {} retrieved ({} tweets / {} images)...",
-        username,
-        tweets_retrieved,
-        urls.len()
-    ));
+fn validate_local_address(address: String) -> Result<(), String> {
+    address
+        .parse::<std::net::IpAddr>()
+        .map(|_| ())
+        .map_err(|_| format!("Invalid IP address: {}", address))
+}
 
-    urls
+/// Arguments deciding which images are downloaded and where they are stored.
+fn selection_args<'a, 'b>() -> [Arg<'a, 'b>; 22] {
+    [
+        Arg::with_name("media")
+            .long("media")
+            .value_name("KINDS")
+            .help("Kinds of media to download: photos, gifs, videos or all, combined with + (eg. photos+gifs) [default: photos or the setting of the account in the configuration file]")
+            .takes_value(true)
+            .validator(|kinds| filter::MediaKinds::parse(&kinds).map(|_| ())),
+        Arg::with_name("newer_than")
+            .long("newer-than")
+            .value_name("AGE")
+            .help("Only download images of tweets newer than the given age (eg. 12h, 30d, 2w, 6m, 1y)")
+            .takes_value(true)
+            .validator(|age| filter::parse_age(&age).map(|_| ())),
+        Arg::with_name("older_than")
+            .long("older-than")
+            .value_name("AGE")
+            .help("Only download images of tweets older than the given age (eg. 12h, 30d, 2w, 6m, 1y)")
+            .takes_value(true)
+            .validator(|age| filter::parse_age(&age).map(|_| ())),
+        Arg::with_name("since_date")
+            .long("since-date")
+            .value_name("DATE")
+            .help("Only download images of tweets posted on or after the given date (eg. 2021-03-14)")
+            .takes_value(true)
+            .validator(|date| filter::parse_date(&date, false).map(|_| ())),
+        Arg::with_name("until_date")
+            .long("until-date")
+            .value_name("DATE")
+            .help("Only download images of tweets posted on or before the given date (eg. 2021-03-14)")
+            .takes_value(true)
+            .validator(|date| filter::parse_date(&date, true).map(|_| ())),
+        Arg::with_name("min_likes")
+            .long("min-likes")
+            .value_name("N")
+            .help("Only download images of tweets liked at least N times (not available with --via-nitter)")
+            .takes_value(true)
+            .validator(validate_count),
+        Arg::with_name("min_retweets")
+            .long("min-retweets")
+            .value_name("N")
+            .help("Only download images of tweets retweeted at least N times (not available with --via-nitter)")
+            .takes_value(true)
+            .validator(validate_count),
+        Arg::with_name("match_text")
+            .long("match-text")
+            .value_name("REGEX")
+            .help("Only download images of tweets, whose text matches the given regular expression (not available with --via-nitter)")
+            .takes_value(true)
+            .validator(validate_regex),
+        Arg::with_name("exclude_text")
+            .long("exclude-text")
+            .value_name("REGEX")
+            .help("Skip images of tweets, whose text matches the given regular expression (not available with --via-nitter)")
+            .takes_value(true)
+            .validator(validate_regex),
+        Arg::with_name("exclude_keywords")
+            .long("exclude-keywords")
+            .value_name("KEYWORDS")
+            .help("Skip images of tweets containing any of the given comma separated terms, ignoring case (eg. giveaway,spoiler) (not available with --via-nitter)")
+            .takes_value(true),
+        Arg::with_name("with_hashtag")
+            .long("with-hashtag")
+            .value_name("HASHTAG")
+            .help("Only download images of tweets carrying the given hashtag, may be given multiple times to accept any of them (not available with --via-nitter)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+        Arg::with_name("min_width")
+            .long("min-width")
+            .value_name("PIXELS")
+            .help("Only download images at least PIXELS wide, as far as twitter offers them (not available with --via-nitter)")
+            .takes_value(true)
+            .validator(validate_count),
+        Arg::with_name("min_height")
+            .long("min-height")
+            .value_name("PIXELS")
+            .help("Only download images at least PIXELS high, as far as twitter offers them (not available with --via-nitter)")
+            .takes_value(true)
+            .validator(validate_count),
+        Arg::with_name("skip_sensitive")
+            .long("skip-sensitive")
+            .help("Skip tweets flagged as possibly sensitive (not available with --via-nitter)")
+            .conflicts_with("only_sensitive"),
+        Arg::with_name("only_sensitive")
+            .long("only-sensitive")
+            .help("Only download media of tweets flagged as possibly sensitive (not available with --via-nitter)"),
+        Arg::with_name("min_photos_per_tweet")
+            .long("min-photos-per-tweet")
+            .value_name("N")
+            .help("Only download media of tweets carrying at least N photos, eg. photo dumps or comic pages")
+            .takes_value(true)
+            .validator(|value| match value.parse::<usize>() {
+                Ok(count) if count > 0 => Ok(()),
+                _ => Err(format!("Number of photos must be a positive number: {}", value)),
+            }),
+        Arg::with_name("formats")
+            .long("formats")
+            .value_name("FORMATS")
+            .help("Only download files of the given comma separated formats, as given by their url or Content-Type (eg. jpg,png)")
+            .takes_value(true),
+        Arg::with_name("since_id")
+            .long("since-id")
+            .value_name("ID")
+            .help("Only download images of tweets newer than the tweet with the given id (overrides --incremental)")
+            .takes_value(true)
+            .validator(validate_tweet_id),
+        Arg::with_name("max_id")
+            .long("max-id")
+            .value_name("ID")
+            .help("Only download images of the tweet with the given id and older ones")
+            .takes_value(true)
+            .validator(validate_tweet_id),
+        Arg::with_name("filename_template")
+            .long("filename-template")
+            .value_name("TEMPLATE")
+            .help("Template for stored filenames ({filename}, {author}, {original_author}, {tweet_id}, {media_id}, {year}, {month}, {day})")
+            .takes_value(true)
+            .default_value("{filename}"),
+        Arg::with_name("per_user_dirs")
+            .long("per-user-dirs")
+            .help("Store images in a subdirectory named after the user inside the output directory"),
+        Arg::with_name("organize_by")
+            .long("organize-by")
+            .value_name("LAYOUT")
+            .help("Organize images in subdirectories (date: YYYY/MM/ of the tweet)")
+            .takes_value(true)
+            .possible_values(&["date"]),
+    ]
 }
 
-async fn download_urls(urls: Vec<String>, max_requests: u32, target_directory: String) {
-    let multi_progress = MultiProgress::with_draw_target(ProgressDrawTarget::stdout());
-    let main_progress = multi_progress.add(ProgressBar::new(urls.len() as u64));
-    main_progress.set_prefix("Downloading Images");
-    let mut spinners: Vec<ProgressBar> = vec![];
-    for _ in 0..max_requests {
-        let spinner = multi_progress.add(ProgressBar::new_spinner());
-        spinner.enable_steady_tick(80);
-        spinners.push(spinner);
+/// The filename template including the subdirectories requested by the given arguments.
+fn filename_template(matches: &ArgMatches) -> String {
+    let mut filename_template = matches.value_of("filename_template").unwrap().to_string();
+    if matches.value_of("organize_by") == Some("date") {
+        filename_template = format!("{{year}}/{{month}}/{}", filename_template);
+    }
+    if matches.is_present("per_user_dirs") {
+        filename_template = format!("{{author}}/{}", filename_template);
     }
+    filename_template
+}
 
-    // Ensure that the multiprogress is properly rendered.
-    let multi_progress_join_handle =
-        tokio::task::spawn_blocking(move || multi_progress.join().unwrap());
-
-    let fetches = futures::stream::iter(urls.into_iter().enumerate().map(|(index, url)| {
-        let spinner = &spinners[index % max_requests as usize];
-        let progress = &main_progress;
-        let target_directory = &target_directory;
-        async move {
-            spinner.set_message(format!("Downloading: {}", url));
-            let response = reqwest::get(&url)
-                .await
-                .expect(&format!("Could not download url {}", url));
-            let bytes = response.bytes().await.expect(&format!(
-                "Could not retrieve download result for url {}",
-                url
-            ));
-            let parsed_url =
-                Url::parse(url.as_str()).expect(&format!("Could not parse URL: {}", url));
-            match parsed_url.path().split("/").last() {
-                Some(file_name) => {
-                    let path = format!("{}/{}", target_directory, file_name);
-                    let mut f = tokio::fs::File::create(&path)
-                        .await
-                        .expect(&format!("Could not open file for writing {}", path));
-                    f.write_all(&bytes)
-                        .await
-                        .expect(&format!("Could not write file {}", path));
-                }
-                None => panic!("Could not extract filename from url {}", url),
-            }
-            progress.inc(1);
+/// The filter for the tweets of the given user requested by the given arguments.
+fn tweet_filter(
+    matches: &ArgMatches,
+    config: &config::Config,
+    username: &str,
+    since_id: Option<u64>,
+) -> TweetFilter {
+    let now = chrono::Utc::now();
+    let age_bound = |name: &str| {
+        matches.value_of(name).map(|age| {
+            now.checked_sub_signed(filter::parse_age(age).unwrap())
+                .unwrap_or(chrono::MIN_DATETIME)
+        })
+    };
+    let date_bound = |name: &str, end_of_day| {
+        matches
+            .value_of(name)
+            .map(|date| filter::parse_date(date, end_of_day).unwrap())
+    };
+    // If both a relative and an absolute bound are given, the stricter one applies.
+    let newer_than = age_bound("newer_than").max(date_bound("since_date", false));
+    let older_than = match (age_bound("older_than"), date_bound("until_date", true)) {
+        (Some(age), Some(date)) => Some(age.min(date)),
+        (age, date) => age.or(date),
+    };
+    TweetFilter {
+        newer_than,
+        older_than,
+        media_kinds: match matches
+            .value_of("media")
+            .or_else(|| config.account(username)?.media.as_deref())
+        {
+            Some(kinds) => filter::MediaKinds::parse(kinds).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(exit_code::USAGE_ERROR);
+            }),
+            None => filter::MediaKinds::default(),
+        },
+        since_id: matches
+            .value_of("since_id")
+            .map(|id| id.parse().unwrap())
+            .or(since_id),
+        max_id: matches.value_of("max_id").map(|id| id.parse().unwrap()),
+        min_likes: matches
+            .value_of("min_likes")
+            .map(|count| count.parse().unwrap()),
+        min_retweets: matches
+            .value_of("min_retweets")
+            .map(|count| count.parse().unwrap()),
+        match_text: matches
+            .value_of("match_text")
+            .map(|pattern| regex::Regex::new(pattern).unwrap()),
+        exclude_text: matches
+            .value_of("exclude_text")
+            .map(|pattern| regex::Regex::new(pattern).unwrap()),
+        exclude_keywords: matches
+            .value_of("exclude_keywords")
+            .map(|keywords| {
+                keywords
+                    .split(',')
+                    .map(|keyword| keyword.trim().to_lowercase())
+                    .filter(|keyword| !keyword.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        min_width: matches
+            .value_of("min_width")
+            .map(|width| width.parse().unwrap()),
+        min_height: matches
+            .value_of("min_height")
+            .map(|height| height.parse().unwrap()),
+        sensitive: match (
+            matches.is_present("skip_sensitive"),
+            matches.is_present("only_sensitive"),
+        ) {
+            (true, _) => Some(false),
+            (_, true) => Some(true),
+            _ => None,
+        },
+        min_photos: matches
+            .value_of("min_photos_per_tweet")
+            .map(|count| count.parse().unwrap()),
+        formats: matches
+            .value_of("formats")
+            .map(|formats| {
+                formats
+                    .split(',')
+                    .filter(|format| !format.trim().is_empty())
+                    .map(filter::normalize_format)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        hashtags: matches
+            .values_of("with_hashtag")
+            .map(|hashtags| {
+                hashtags
+                    .map(|hashtag| hashtag.trim_start_matches('#').to_lowercase())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Arguments needed to authenticate against the twitter API.
+fn credential_args<'a, 'b>() -> [Arg<'a, 'b>; 4] {
+    [
+        Arg::with_name("consumer_key")
+            .short("k")
+            .long("consumer-key")
+            .value_name("KEY")
+            .help("Twiter API Consumer Key")
+            .takes_value(true),
+        Arg::with_name("consumer_secret")
+            .short("c")
+            .long("consumer-secret")
+            .value_name("SECRET")
+            .help("Twiter API Consumer Secret")
+            .takes_value(true),
+        Arg::with_name("access_token")
+            .short("t")
+            .long("access-token")
+            .value_name("TOKEN")
+            .help("Twiter API Access Token")
+            .takes_value(true),
+        Arg::with_name("access_token_secret")
+            .short("s")
+            .long("access-token-secret")
+            .value_name("SECRET")
+            .help("Twiter API Access Token Secret")
+            .takes_value(true),
+    ]
+}
+
+/// Build the API token from the commandline, falling back to the configuration file for every
+/// credential not given explicitly.
+fn token_from_matches(matches: &ArgMatches, config: &config::Config) -> egg_mode::Token {
+    let credential = |name: &str, configured: &Option<String>| match matches
+        .value_of(name)
+        .map(str::to_string)
+        .or_else(|| configured.clone())
+    {
+        Some(value) => value,
+        None => {
+            eprintln!(
+                    "Missing twitter API credentials. Provide them on the commandline or run `twitter_image_downloader init`."
+                );
+            std::process::exit(exit_code::AUTH_FAILURE);
         }
-    }))
-    .buffer_unordered(max_requests as usize)
-    .collect::<Vec<()>>();
-    fetches.await;
+    };
 
-    for spinner in spinners.iter() {
-        spinner.finish_and_clear();
+    access_token(
+        credential("consumer_key", &config.consumer_key),
+        credential("consumer_secret", &config.consumer_secret),
+        credential("access_token", &config.access_token),
+        credential("access_token_secret", &config.access_token_secret),
+    )
+}
+
+/// Load the configuration file, completing missing credentials from `~/.netrc`.
+fn load_config(path: &std::path::Path) -> config::Config {
+    let mut config = config::Config::load(path);
+    if let Some(entry) = netrc::lookup("api.twitter.com") {
+        config.apply_netrc(entry);
     }
+    config
+}
 
-    main_progress.finish();
-    multi_progress_join_handle.await.unwrap();
+/// Directory holding the state, either given explicitly or inside the output directory.
+fn state_directory(matches: &ArgMatches, output_directory: &std::path::Path) -> std::path::PathBuf {
+    match matches.value_of("state_directory") {
+        Some(directory) => std::path::PathBuf::from(directory),
+        None => output_directory.join(".twitter_image_downloader"),
+    }
+}
+
+/// Definition of the commandline interface, with directories defaulting to the given one.
+fn app(current_working_directory: &str) -> App<'_, '_> {
+    App::new("Twitter Image Downloader")
+    .version("1.0")
+    .author("Jakob Westhoff <jakob@westhoffswelt.de>")
+    .about("Download posted images from a given twitter user")
+    .setting(AppSettings::SubcommandsNegateReqs)
+    .args(&credential_args())
+    .arg(
+        Arg::with_name("config")
+            .long("config")
+            .value_name("FILENAME")
+            .help("Configuration file to read credentials from [default: <config dir>/twitter_image_downloader/config.toml]")
+            .takes_value(true)
+            .global(true),
+    )
+    .arg(
+        Arg::with_name("number_of_images")
+            .short("n")
+            .long("max-images")
+            .value_name("N")
+            .help("Maximal number of images to download")
+            .takes_value(true)
+            .default_value("0"),
+    )
+    .arg(
+        Arg::with_name("max_tweets")
+            .long("max-tweets")
+            .value_name("N")
+            .help("Maximal number of tweets to scan for images, regardless of how many images they contain")
+            .takes_value(true)
+            .validator(|value| match value.parse::<u32>() {
+                Ok(count) if count > 0 => Ok(()),
+                _ => Err(format!("Number of tweets must be a positive number: {}", value)),
+            }),
+    )
+    .arg(
+        Arg::with_name("max_requests")
+            .short("m")
+            .long("max-requests")
+            .value_name("N")
+            .help("Maximal number of parallel download requests")
+            .takes_value(true)
+            .default_value("4"),
+    )
+    .arg(
+        Arg::with_name("output_directory")
+            .short("o")
+            .long("output-directory")
+            .value_name("DIRECTORY")
+            .help("Directory to storage downloaded images in")
+            .takes_value(true)
+            .default_value(current_working_directory),
+    )
+    .arg(
+        Arg::with_name("state_directory")
+            .long("state-directory")
+            .value_name("DIRECTORY")
+            .help("Directory to persist state (eg. the download queue) between runs in, together with the cookies, which are kept in the local data directory of the user otherwise [default: <DIRECTORY>/.twitter_image_downloader]")
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("download_window")
+            .long("download-window")
+            .value_name("HH:MM-HH:MM")
+            .help("Only download images during the given time of day (eg. 01:00-06:00)")
+            .takes_value(true)
+            .validator(|window| schedule::DownloadWindow::parse(&window).map(|_| ())),
+    )
+    .arg(
+        Arg::with_name("output_urls")
+            .short("u")
+            .long("output-url-list")
+            .value_name("FILENAME")
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("page_size")
+            .long("page-size")
+            .value_name("N")
+            .help("Number of tweets requested per timeline page, between 1 and 200 [default: 200]")
+            .takes_value(true)
+            .validator(|value| match value.parse::<u32>() {
+                Ok(1..=200) => Ok(()),
+                _ => Err(format!("Page size must be a number between 1 and 200: {}", value)),
+            }),
+    )
+    .arg(
+        Arg::with_name("include_retweets")
+            .short("r")
+            .long("include-retweets")
+            .help("Include images from retweets and attribute them to their original author"),
+    )
+    .arg(
+        Arg::with_name("include_replies")
+            .long("include-replies")
+            .help("Include images from replies, eg. ones posted in threads"),
+    )
+    .arg(
+        Arg::with_name("with_likes")
+            .long("with-likes")
+            .help("Also download the images of tweets liked by the user into the likes directory")
+            .conflicts_with_all(&["via_nitter", "cookies_from_browser"]),
+    )
+    .arg(
+        Arg::with_name("prefer_source")
+            .long("prefer-source")
+            .value_name("SOURCE")
+            .help("Source an image found on the timeline as well as in the likes is attributed to and stored in [default: timeline]")
+            .takes_value(true)
+            .possible_values(&["timeline", "likes"])
+            .requires("with_likes"),
+    )
+    .args(&selection_args())
+    .arg(
+        Arg::with_name("incremental")
+            .long("incremental")
+            .help("Only retrieve tweets newer than the ones retrieved by the previous incremental run"),
+    )
+    .arg(
+        Arg::with_name("write_metadata")
+            .long("write-metadata")
+            .help("Store the tweet metadata in a <image>.json file next to every image"),
+    )
+    .arg(
+        Arg::with_name("save_text")
+            .long("save-text")
+            .help("Store the full tweet text in a <image>.txt file next to every image"),
+    )
+    .arg(
+        Arg::with_name("dump_tweets")
+            .long("dump-tweets")
+            .value_name("DIRECTORY")
+            .help("Store the complete data of every tweet with media as tweet_<id>.json in DIRECTORY")
+            .takes_value(true)
+            .conflicts_with("via_nitter"),
+    )
+    .arg(
+        Arg::with_name("gallery")
+            .long("gallery")
+            .help("Generate a browsable index.html with thumbnails, tweet texts and links to the tweets in the output directory (implies --write-metadata)")
+            .conflicts_with_all(&["archive", "output"]),
+    )
+    .arg(
+        Arg::with_name("status_page")
+            .long("status-page")
+            .help("Write a status.html showing the last run of every archived and watched account to the output directory"),
+    )
+    .arg(
+        Arg::with_name("embed_metadata")
+            .long("embed-metadata")
+            .help("Embed tweet text, author and url into the images as EXIF and XMP metadata"),
+    )
+    .arg(
+        Arg::with_name("archive")
+            .long("archive")
+            .value_name("FILENAME")
+            .help("Store all images in a single .zip, .tar or .tar.zst archive instead of individual files")
+            .takes_value(true)
+            .validator(|path| archive::Archive::validate(&path))
+            .conflicts_with("verify_size"),
+    )
+    .arg(
+        Arg::with_name("output")
+            .long("output")
+            .value_name("URL")
+            .help("Upload all images to S3 compatible object storage (s3://bucket/prefix) a WebDAV server (webdav://server/path, webdavs:// for HTTPS) or via SFTP (sftp://user@host/path) instead of the output directory")
+            .takes_value(true)
+            .conflicts_with_all(&["archive", "verify_size"]),
+    )
+    .arg(
+        Arg::with_name("s3_endpoint")
+            .long("s3-endpoint")
+            .value_name("URL")
+            .help("Endpoint of a S3 compatible service (eg. MinIO) to use instead of AWS")
+            .takes_value(true)
+            .requires("output"),
+    )
+    .arg(
+        Arg::with_name("s3_region")
+            .long("s3-region")
+            .value_name("REGION")
+            .help("Region of the S3 bucket [default: AWS_REGION or us-east-1]")
+            .takes_value(true)
+            .requires("output"),
+    )
+    .arg(
+        Arg::with_name("connect_timeout")
+            .long("connect-timeout")
+            .value_name("SECONDS")
+            .help("Give up connecting to a server after SECONDS")
+            .takes_value(true)
+            .default_value("30")
+            .validator(validate_timeout),
+    )
+    .arg(
+        Arg::with_name("read_timeout")
+            .long("read-timeout")
+            .value_name("SECONDS")
+            .help("Give up a download, once the server did not send any data for SECONDS")
+            .takes_value(true)
+            .default_value("60")
+            .validator(validate_timeout),
+    )
+    .arg(
+        Arg::with_name("requests_per_second")
+            .long("requests-per-second")
+            .value_name("LIMIT")
+            .help("Start at most LIMIT image requests per second (eg. 5 or 0.5), regardless of the number of concurrent requests")
+            .takes_value(true)
+            .validator(|value| match value.parse::<f64>() {
+                Ok(limit) if limit > 0.0 && limit.is_finite() => Ok(()),
+                _ => Err(format!("Limit must be a positive number: {}", value)),
+            }),
+    )
+    .arg(
+        Arg::with_name("min_file_size")
+            .long("min-file-size")
+            .value_name("SIZE")
+            .help("Skip files smaller than SIZE bytes, which may be followed by K, M or G (eg. 50K)")
+            .takes_value(true)
+            .validator(|size| parse_file_size(&size).map(|_| ())),
+    )
+    .arg(
+        Arg::with_name("max_file_size")
+            .long("max-file-size")
+            .value_name("SIZE")
+            .help("Skip files larger than SIZE bytes, which may be followed by K, M or G (eg. 10M)")
+            .takes_value(true)
+            .validator(|size| parse_file_size(&size).map(|_| ())),
+    )
+    .arg(
+        Arg::with_name("per_file_timeout")
+            .long("per-file-timeout")
+            .value_name("SECONDS")
+            .help("Give up on downloads taking longer than SECONDS and report them as failed, so the other downloads can proceed")
+            .takes_value(true)
+            .validator(validate_timeout),
+    )
+    .arg(
+        Arg::with_name("slow_item_budget")
+            .long("slow-item-budget")
+            .value_name("SECONDS")
+            .help("Defer downloads taking longer than SECONDS to a slow queue, which is processed with fewer concurrent requests after all other downloads")
+            .takes_value(true)
+            .validator(|value| match value.parse::<u64>() {
+                Ok(seconds) if seconds > 0 => Ok(()),
+                _ => Err(format!("Budget must be a positive number of seconds: {}", value)),
+            }),
+    )
+    .arg(
+        Arg::with_name("withheld_proxy")
+            .long("withheld-proxy")
+            .value_name("URL")
+            .help("Proxy to retry images of tweets withheld in your country through (eg. http://proxy.example.com:3128)")
+            .takes_value(true)
+            .validator(validate_proxy),
+    )
+    .arg(
+        Arg::with_name("user_agent")
+            .long("user-agent")
+            .value_name("STRING")
+            .help(concat!("User-Agent header sent with all requests except the ones to the official API, whose client sends none and can not be changed [default: ", env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"), "]"))
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("proxy")
+            .long("proxy")
+            .value_name("URL")
+            .help("HTTP or SOCKS5 proxy (eg. socks5h://127.0.0.1:9050 for Tor) for all requests, only supported with --via-nitter or --cookies-from-browser [default: HTTPS_PROXY environment variable]")
+            .takes_value(true)
+            .validator(validate_proxy),
+    )
+    .arg(
+        Arg::with_name("api_proxy")
+            .long("api-proxy")
+            .value_name("URL")
+            .help("Proxy to send the timeline requests of --via-nitter or --cookies-from-browser through, instead of --proxy")
+            .takes_value(true)
+            .validator(validate_proxy),
+    )
+    .arg(
+        Arg::with_name("media_proxy")
+            .long("media-proxy")
+            .value_name("URL")
+            .help("Proxy to download the images through, instead of --proxy")
+            .takes_value(true)
+            .validator(validate_proxy),
+    )
+    .arg(
+        Arg::with_name("api_local_address")
+            .long("api-local-address")
+            .value_name("IP")
+            .help("Local address (eg. of a VPN interface) to send the timeline requests of --via-nitter or --cookies-from-browser from")
+            .takes_value(true)
+            .validator(validate_local_address),
+    )
+    .arg(
+        Arg::with_name("media_local_address")
+            .long("media-local-address")
+            .value_name("IP")
+            .help("Local address (eg. of a VPN interface) to download the images from")
+            .takes_value(true)
+            .validator(validate_local_address),
+    )
+    .arg(
+        Arg::with_name("redownload")
+            .long("redownload")
+            .help("Download all images again, even if their files already exist (same as --on-conflict overwrite)"),
+    )
+    .arg(
+        Arg::with_name("on_conflict")
+            .long("on-conflict")
+            .value_name("POLICY")
+            .help("What to do if a file of the same name already exists: skip the image, overwrite the file, rename the image to 'name (1).jpg' or stop with an error. Files downloaded from the same url before are always skipped by rename and error [default: skip]")
+            .takes_value(true)
+            .possible_values(&["skip", "overwrite", "rename", "error"])
+            .conflicts_with("redownload"),
+    )
+    .arg(
+        Arg::with_name("verify_size")
+            .long("verify-size")
+            .conflicts_with_all(&["redownload", "on_conflict", "embed_metadata"])
+            .help("Only skip existing files if their size matches the remote file (costs a HEAD request per image)"),
+    )
+    .arg(
+        Arg::with_name("etag_cache")
+            .long("etag-cache")
+            .help("Remember ETag and Last-Modified of every download and check existing files with a conditional request, which only downloads them again if they changed on the server"),
+    )
+    .arg(
+        Arg::with_name("resume")
+            .long("resume")
+            .conflicts_with("retry_failed")
+            .help("Continue the downloads of an interrupted run for the user, without retrieving the timeline again"),
+    )
+    .arg(
+        Arg::with_name("retry_failed")
+            .long("retry-failed")
+            .value_name("FILENAME")
+            .help("Only download the images listed in a failed_urls.txt written by an earlier run, without retrieving the timeline again")
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("verify_checksum")
+            .long("verify-checksum")
+            .help("Verify downloads against the checksum announced by the server in a Digest or Content-MD5 header and download mismatches again"),
+    )
+    .arg(
+        Arg::with_name("preview_directory")
+            .long("preview-directory")
+            .value_name("DIRECTORY")
+            .help("Download the original size of every image and store an additional small preview in a parallel tree inside the given directory")
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("write_manifest")
+            .long("write-manifest")
+            .help("Append a JSON record (url, path, tweet id, size and status) of every processed image to <DIRECTORY>/manifest.ndjson"),
+    )
+    .arg(
+        Arg::with_name("write_summary")
+            .long("write-summary")
+            .help("Write the totals of the run (tweets scanned, images found, downloaded, skipped, failed, bytes, wall time and throughput) to <DIRECTORY>/summary.json"),
+    )
+    .arg(
+        Arg::with_name("export_csv")
+            .long("export-csv")
+            .value_name("FILENAME")
+            .help("Write tweet id, date, tweet url, media url and saved filename of every image to a CSV file")
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("archive_db")
+            .long("archive-db")
+            .help("Record every downloaded image with its tweet, url, path and SHA-256 hash in <DIRECTORY>/archive.db and skip images already stored under a different name"),
+    )
+    .arg(
+        Arg::with_name("download_mtime")
+            .long("download-mtime")
+            .help("Keep the time of download as modification time instead of the creation time of the tweet"),
+    )
+    .arg(
+        Arg::with_name("via_nitter")
+            .long("via-nitter")
+            .value_name("URL")
+            .help("Scrape the media tab of the given Nitter instance instead of using the twitter API")
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("cookies_from_browser")
+            .long("cookies-from-browser")
+            .value_name("BROWSER[:DATABASE]")
+            .help("Authenticate using the twitter session cookies of a browser (firefox, or chrome and chromium on Linux without a system keyring) instead of API credentials")
+            .takes_value(true)
+            .conflicts_with("via_nitter"),
+    )
+    .arg(
+        Arg::with_name("heap_stats")
+            .long("heap-stats")
+            .help("Periodically log memory usage and queue depths to stderr (needs a build with the heap-stats feature)"),
+    )
+    .arg(
+        Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .help("Log debug information about API requests and downloads to stderr, twice for trace output (overridden by RUST_LOG)")
+            .multiple(true)
+            .global(true),
+    )
+    .arg(
+        Arg::with_name("color")
+            .long("color")
+            .value_name("WHEN")
+            .help("Whether to use colors in the output, auto disables them if the output is no terminal or NO_COLOR is set")
+            .takes_value(true)
+            .possible_values(&["never", "auto", "always"])
+            .default_value("auto")
+            .global(true),
+    )
+    .arg(
+        Arg::with_name("log_file")
+            .long("log-file")
+            .value_name("FILENAME")
+            .help("Append timestamped events (fetched pages, processed and failed urls) to the given file, independent of the terminal output")
+            .takes_value(true)
+            .global(true),
+    )
+    .arg(
+        Arg::with_name("progress")
+            .long("progress")
+            .value_name("WHEN")
+            .help("Whether to draw the progress display: never, always, or auto to fall back to periodic status lines if stderr is no capable terminal (eg. in cron jobs and CI logs) [default: auto]")
+            .takes_value(true)
+            .possible_values(&["never", "auto", "always"])
+            .conflicts_with_all(&["plain", "quiet", "json_progress"]),
+    )
+    .arg(
+        Arg::with_name("plain")
+            .long("plain")
+            .help("Print linear, screen reader friendly status lines instead of spinners and progress bars"),
+    )
+    .arg(
+        Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .help("Suppress the progress display and informational messages, only warnings and errors are printed to stderr")
+            .conflicts_with("plain"),
+    )
+    .arg(
+        Arg::with_name("json_progress")
+            .long("json-progress")
+            .help("Print one JSON object per event (page fetched, file started, file done, error) to stdout instead of the progress display, for scripts and GUIs")
+            .conflicts_with("plain"),
+    )
+    .arg(
+        Arg::with_name("print_urls")
+            .long("print-urls")
+            .help("Print the url of every found image to stdout as soon as it is found, while progress and status messages go to stderr")
+            .conflicts_with("json_progress"),
+    )
+    .arg(
+        Arg::with_name("plain_interval")
+            .long("plain-interval")
+            .value_name("PERCENT")
+            .help("Report the download progress every PERCENT percent in plain mode [default: 10]")
+            .takes_value(true)
+            .requires("plain")
+            .validator(|value| match value.parse::<u64>() {
+                Ok(1..=100) => Ok(()),
+                _ => Err(format!("Interval must be a percentage between 1 and 100: {}", value)),
+            }),
+    )
+    .arg(
+        Arg::with_name("record_fixtures")
+            .long("record-fixtures")
+            .value_name("DIRECTORY")
+            .takes_value(true)
+            .hidden(true),
+    )
+    .arg(
+        Arg::with_name("replay_fixtures")
+            .long("replay-fixtures")
+            .value_name("DIRECTORY")
+            .takes_value(true)
+            .conflicts_with("record_fixtures")
+            .hidden(true),
+    )
+    .arg(
+        Arg::with_name("username")
+            .help("Twitter username to download images from.")
+            .value_name("USERNAME")
+            .required(true)
+            .index(1),
+    )
+    .subcommand(
+        SubCommand::with_name("auth")
+            .about("Manage the twitter API credentials")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("verify")
+                    .about("Verify the given credentials and show the account they belong to")
+                    .args(&credential_args()),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("discover")
+            .about("Rank the image posting accounts retweeted or quoted by an archived user and offer to watch them")
+            .arg(
+                Arg::with_name("output_directory")
+                    .short("o")
+                    .long("output-directory")
+                    .value_name("DIRECTORY")
+                    .help("Directory the user has been archived to")
+                    .takes_value(true)
+                    .default_value(current_working_directory),
+            )
+            .arg(
+                Arg::with_name("limit")
+                    .short("l")
+                    .long("limit")
+                    .value_name("NUMBER")
+                    .help("Number of accounts to show")
+                    .takes_value(true)
+                    .default_value("10")
+                    .validator(|value| match value.parse::<usize>() {
+                        Ok(_) => Ok(()),
+                        Err(_) => Err(format!("Limit must be a number: {}", value)),
+                    }),
+            )
+            .arg(
+                Arg::with_name("username")
+                    .help("Archived twitter username")
+                    .value_name("USERNAME")
+                    .required(true)
+                    .index(1),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("tag")
+            .about("Attach tags (eg. license notes or usage rights) to archived images")
+            .arg(
+                Arg::with_name("output_directory")
+                    .short("o")
+                    .long("output-directory")
+                    .value_name("DIRECTORY")
+                    .help("Directory the images have been archived to")
+                    .takes_value(true)
+                    .default_value(current_working_directory),
+            )
+            .arg(
+                Arg::with_name("state_directory")
+                    .long("state-directory")
+                    .value_name("DIRECTORY")
+                    .help("Directory the state has been persisted to [default: <DIRECTORY>/.twitter_image_downloader]")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("add")
+                    .short("a")
+                    .long("add")
+                    .value_name("TAG")
+                    .help("Tag to add")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1),
+            )
+            .arg(
+                Arg::with_name("remove")
+                    .short("r")
+                    .long("remove")
+                    .value_name("TAG")
+                    .help("Tag to remove")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1),
+            )
+            .arg(
+                Arg::with_name("files")
+                    .help("Archived images to tag. Without --add or --remove their tags are shown.")
+                    .value_name("FILE")
+                    .required(true)
+                    .multiple(true)
+                    .index(1),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("verify-remote")
+            .about("Download a sample of the archived images again and check them against the hashes recorded in the archive database")
+            .arg(
+                Arg::with_name("output_directory")
+                    .short("o")
+                    .long("output-directory")
+                    .value_name("DIRECTORY")
+                    .help("Directory the images have been archived to")
+                    .takes_value(true)
+                    .default_value(current_working_directory),
+            )
+            .arg(
+                Arg::with_name("sample")
+                    .long("sample")
+                    .value_name("NUMBER")
+                    .help("Number of randomly chosen images to verify")
+                    .takes_value(true)
+                    .default_value("20")
+                    .validator(|value| match value.parse::<usize>() {
+                        Ok(_) => Ok(()),
+                        Err(_) => Err(format!("Sample size must be a number: {}", value)),
+                    }),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("adopt")
+            .about("Index images downloaded by older versions or other tools, so they are not downloaded again")
+            .arg(
+                Arg::with_name("output_directory")
+                    .short("o")
+                    .long("output-directory")
+                    .value_name("DIRECTORY")
+                    .help("Directory containing the downloaded images")
+                    .takes_value(true)
+                    .default_value(current_working_directory),
+            )
+            .arg(
+                Arg::with_name("offline")
+                    .long("offline")
+                    .help("Only use the file names and contents, without looking up the tweets through the API"),
+            )
+            .args(&credential_args()),
+    )
+    .subcommand(
+        SubCommand::with_name("explain")
+            .about("Show how a download would treat a single tweet: its media, the filters, the resulting filenames and whether they are already downloaded")
+            .arg(
+                Arg::with_name("tweet")
+                    .long("tweet")
+                    .value_name("URL")
+                    .help("Url or id of the tweet")
+                    .takes_value(true)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("output_directory")
+                    .short("o")
+                    .long("output-directory")
+                    .value_name("DIRECTORY")
+                    .help("Directory the images would be downloaded to")
+                    .takes_value(true)
+                    .default_value(current_working_directory),
+            )
+            .args(&selection_args())
+            .args(&credential_args()),
+    )
+    .subcommand(
+        SubCommand::with_name("regen")
+            .about("Rebuild the thumbnails and the HTML gallery of an archive from the original files using all CPU cores")
+            .arg(
+                Arg::with_name("output_directory")
+                    .short("o")
+                    .long("output-directory")
+                    .value_name("DIRECTORY")
+                    .help("Directory the images have been archived to")
+                    .takes_value(true)
+                    .default_value(current_working_directory),
+            )
+            .arg(
+                Arg::with_name("gallery")
+                    .long("gallery")
+                    .help("Rebuild <DIRECTORY>/index.html (rebuilds everything if neither --gallery nor --thumbnails is given)"),
+            )
+            .arg(
+                Arg::with_name("thumbnails")
+                    .long("thumbnails")
+                    .help("Rebuild the thumbnails in <DIRECTORY>/.thumbnails"),
+            )
+            .arg(
+                Arg::with_name("thumbnail_size")
+                    .long("thumbnail-size")
+                    .value_name("PIXELS")
+                    .help("Maximum width and height of the thumbnails [default: 320]")
+                    .takes_value(true)
+                    .validator(|value| match value.parse::<u32>() {
+                        Ok(size) if size > 0 => Ok(()),
+                        _ => Err(format!("Thumbnail size must be a positive number: {}", value)),
+                    }),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("browse")
+            .about("Interactively browse the archived images by user or month and open them in the system viewer")
+            .arg(
+                Arg::with_name("output_directory")
+                    .short("o")
+                    .long("output-directory")
+                    .value_name("DIRECTORY")
+                    .help("Directory the images have been archived to")
+                    .takes_value(true)
+                    .default_value(current_working_directory),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("init")
+            .about("Interactively set up the API credentials and write the configuration file"),
+    )
+    .subcommand(
+        SubCommand::with_name("completions")
+            .about("Print completions of all subcommands and options for the given shell to stdout")
+            .arg(
+                Arg::with_name("shell")
+                    .value_name("SHELL")
+                    .help("Shell to generate the completions for")
+                    .required(true)
+                    .possible_values(&Shell::variants()),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("manpage")
+            .about("Print the manual page, including the filename template placeholders and the filter syntax, as roff to stdout"),
+    )
 }
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
+    exit_code::install_panic_hook();
     let current_working_directory = std::env::current_dir().unwrap();
-    let matches = App::new("Twitter Image Downloader")
-        .version("1.0")
-        .author("Jakob Westhoff <jakob@westhoffswelt.de>")
-        .about("Download posted images from a given twitter user")
-        .arg(
-            Arg::with_name("consumer_key")
-                .short("k")
-                .long("consumer-key")
-                .value_name("KEY")
-                .help("Twiter API Consumer Key")
-                .takes_value(true)
-                .required(true),
-        )
-        .arg(
-            Arg::with_name("consumer_secret")
-                .short("c")
-                .long("consumer-secret")
-                .value_name("SECRET")
-                .help("Twiter API Consumer Secret")
-                .takes_value(true)
-                .required(true),
-        )
-        .arg(
-            Arg::with_name("access_token")
-                .short("t")
-                .long("access-token")
-                .value_name("TOKEN")
-                .help("Twiter API Access Token")
-                .takes_value(true)
-                .required(true),
-        )
-        .arg(
-            Arg::with_name("access_token_secret")
-                .short("s")
-                .long("access-token-secret")
-                .value_name("SECRET")
-                .help("Twiter API Access Token Secret")
-                .takes_value(true)
-                .required(true),
-        )
-        .arg(
-            Arg::with_name("number_of_images")
-                .short("n")
-                .long("max-images")
-                .value_name("N")
-                .help("Maximal number of images to download")
-                .takes_value(true)
-                .default_value("0"),
-        )
-        .arg(
-            Arg::with_name("max_requests")
-                .short("m")
-                .long("max-requests")
-                .value_name("N")
-                .help("Maximal number of parallel download requests")
-                .takes_value(true)
-                .default_value("4"),
-        )
-        .arg(
-            Arg::with_name("output_directory")
-                .short("o")
-                .long("output-directory")
-                .value_name("DIRECTORY")
-                .help("Directory to storage downloaded images in")
-                .takes_value(true)
-                .default_value(current_working_directory.to_str().unwrap()),
-        )
-        .arg(
-            Arg::with_name("output_urls")
-                .short("u")
-                .long("output-url-list")
-                .value_name("FILENAME")
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("username")
-                .help("Twitter username to download images from.")
-                .value_name("USERNAME")
-                .required(true)
-                .index(1),
-        )
-        .get_matches();
+    let current_working_directory = current_working_directory.to_str().unwrap();
+    let mut cli = app(current_working_directory);
+    // Usage errors are printed before --color is known.
+    if std::env::var_os("NO_COLOR").is_some() {
+        cli = cli.setting(AppSettings::ColorNever);
+    }
+    let matches = cli.get_matches_safe().unwrap_or_else(|err| {
+        // Help and version are reported as error as well, but are no failures.
+        if !err.use_stderr() {
+            err.exit();
+        }
+        eprintln!("{}", err.message);
+        std::process::exit(exit_code::USAGE_ERROR);
+    });
+
+    terminal::set_colors(matches.value_of("color").unwrap());
+    let _run_span = logging::init(
+        matches.occurrences_of("verbose"),
+        matches.value_of("log_file").map(std::path::Path::new),
+    );
+    progress::set_mode(terminal::progress_mode(&matches));
+
+    let config_path = match matches.value_of("config") {
+        Some(path) => std::path::PathBuf::from(path),
+        None => config::default_path(),
+    };
+
+    match matches.subcommand() {
+        ("completions", Some(completions_matches)) => {
+            let shell = completions_matches
+                .value_of("shell")
+                .unwrap()
+                .parse::<Shell>()
+                .unwrap();
+            app(current_working_directory).gen_completions_to(
+                env!("CARGO_PKG_NAME"),
+                shell,
+                &mut std::io::stdout(),
+            );
+            return;
+        }
+        ("manpage", Some(_)) => {
+            // Directories default to the current one, whichever it is when the page is read.
+            manpage::write(&app("."), &mut std::io::stdout().lock())
+                .expect("Could not write the manual page");
+            return;
+        }
+        ("auth", Some(auth_matches)) => {
+            if let ("verify", Some(verify_matches)) = auth_matches.subcommand() {
+                let config = load_config(&config_path);
+                if let Err(reason) =
+                    auth::verify(&token_from_matches(verify_matches, &config)).await
+                {
+                    eprintln!("{}", reason);
+                    std::process::exit(exit_code::AUTH_FAILURE);
+                }
+            }
+            return;
+        }
+        ("discover", Some(discover_matches)) => {
+            discover::discover(
+                &config_path,
+                std::path::Path::new(discover_matches.value_of("output_directory").unwrap()),
+                discover_matches.value_of("username").unwrap(),
+                discover_matches
+                    .value_of("limit")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap(),
+            );
+            return;
+        }
+        ("tag", Some(tag_matches)) => {
+            let output_directory =
+                std::fs::canonicalize(tag_matches.value_of("output_directory").unwrap())
+                    .unwrap_or_else(|_| {
+                        eprintln!("Output directory does not exist");
+                        std::process::exit(exit_code::USAGE_ERROR);
+                    });
+            let values = |name| {
+                tag_matches
+                    .values_of(name)
+                    .map(|values| values.collect::<Vec<_>>())
+                    .unwrap_or_default()
+            };
+            tags::tag(
+                &output_directory,
+                &state_directory(tag_matches, &output_directory),
+                &values("files"),
+                &values("add"),
+                &values("remove"),
+            );
+            return;
+        }
+        ("verify-remote", Some(verify_matches)) => {
+            let client = pooled(reqwest::Client::builder())
+                .user_agent(DEFAULT_USER_AGENT)
+                .build()
+                .expect("Could not initialize http client");
+            verify_remote::verify_remote(
+                &client,
+                std::path::Path::new(verify_matches.value_of("output_directory").unwrap()),
+                verify_matches
+                    .value_of("sample")
+                    .unwrap()
+                    .parse::<usize>()
+                    .unwrap(),
+            )
+            .await;
+            return;
+        }
+        ("adopt", Some(adopt_matches)) => {
+            let token = if adopt_matches.is_present("offline") {
+                None
+            } else {
+                let config = load_config(&config_path);
+                Some(token_from_matches(adopt_matches, &config))
+            };
+            adopt::adopt(
+                std::path::Path::new(adopt_matches.value_of("output_directory").unwrap()),
+                token.as_ref(),
+            )
+            .await;
+            return;
+        }
+        ("explain", Some(explain_matches)) => {
+            let config = load_config(&config_path);
+            let token = token_from_matches(explain_matches, &config);
+            let tweet = explain::lookup(explain_matches.value_of("tweet").unwrap(), &token).await;
+            let username = screen_name(&tweet).unwrap_or_default();
+            explain::explain(
+                &tweet,
+                &tweet_filter(explain_matches, &config, &username, None),
+                &naming::Template::new(&filename_template(explain_matches)),
+                std::path::Path::new(explain_matches.value_of("output_directory").unwrap()),
+            );
+            return;
+        }
+        ("regen", Some(regen_matches)) => {
+            let everything =
+                !regen_matches.is_present("gallery") && !regen_matches.is_present("thumbnails");
+            gallery::regen(
+                std::path::Path::new(regen_matches.value_of("output_directory").unwrap()),
+                everything || regen_matches.is_present("gallery"),
+                everything || regen_matches.is_present("thumbnails"),
+                regen_matches
+                    .value_of("thumbnail_size")
+                    .map(|size| size.parse().unwrap())
+                    .unwrap_or(gallery::DEFAULT_THUMBNAIL_SIZE),
+            );
+            return;
+        }
+        ("browse", Some(browse_matches)) => {
+            browse::browse(std::path::Path::new(
+                browse_matches.value_of("output_directory").unwrap(),
+            ));
+            return;
+        }
+        ("init", Some(_)) => {
+            init::init(&config_path).await;
+            return;
+        }
+        _ => {}
+    }
+    let config = load_config(&config_path);
 
     let output_directory = matches.value_of("output_directory").unwrap();
-    std::fs::create_dir_all(output_directory.clone()).expect(
-        format!(
+    std::fs::create_dir_all(output_directory).unwrap_or_else(|_| {
+        panic!(
             "Target directory '{:?}' could not be created.",
             output_directory
         )
-        .as_str(),
-    );
+    });
     let canonicalized_directory = std::fs::canonicalize(output_directory).unwrap();
 
+    let state_directory = state_directory(&matches, &canonicalized_directory);
+    std::fs::create_dir_all(&state_directory).unwrap_or_else(|_| {
+        panic!(
+            "State directory '{:?}' could not be created.",
+            state_directory
+        )
+    });
+    // Without an explicit state directory, the session cookies are kept out of the output
+    // directory, which may be served by a web server.
+    let cookie_jar = match matches.value_of("state_directory") {
+        Some(_) => cookies::CookieJar::load(&state_directory, None),
+        None => {
+            let cookie_directory = cookies::default_directory();
+            std::fs::create_dir_all(&cookie_directory).unwrap_or_else(|_| {
+                panic!(
+                    "Cookie directory '{:?}' could not be created.",
+                    cookie_directory
+                )
+            });
+            cookies::CookieJar::load(&cookie_directory, Some(&state_directory))
+        }
+    };
+    if let Some(browser) = matches.value_of("cookies_from_browser") {
+        match browser::read_cookies(browser) {
+            Ok(browser_cookies) => {
+                cookie_jar.import(&browser_cookies);
+                cookie_jar.save();
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(exit_code::AUTH_FAILURE);
+            }
+        }
+    }
+    // The official API is accessed through the own http client of egg-mode.
+    if (matches.is_present("api_proxy") || matches.is_present("api_local_address"))
+        && !matches.is_present("via_nitter")
+        && !matches.is_present("cookies_from_browser")
+    {
+        eprintln!(
+            "--api-proxy and --api-local-address are only supported with --via-nitter or --cookies-from-browser"
+        );
+        std::process::exit(exit_code::USAGE_ERROR);
+    }
+    let connect_timeout = std::time::Duration::from_secs(
+        matches
+            .value_of("connect_timeout")
+            .unwrap()
+            .parse()
+            .unwrap(),
+    );
+    let official_api = !matches.is_present("via_nitter")
+        && !matches.is_present("cookies_from_browser")
+        && !matches.is_present("replay_fixtures");
+    // The requests to the official API must not silently bypass the proxy.
+    if matches.is_present("proxy") && official_api {
+        eprintln!(
+            "--proxy is only supported with --via-nitter or --cookies-from-browser, as the client of the official API does not support proxies (use --media-proxy to only route the downloads through a proxy)"
+        );
+        std::process::exit(exit_code::USAGE_ERROR);
+    }
+    if matches.is_present("user_agent") && official_api {
+        eprintln!(
+            "Warning: The official API is accessed with its default User-Agent, as its client does not support changing it."
+        );
+    }
+    let user_agent = matches.value_of("user_agent").unwrap_or(DEFAULT_USER_AGENT);
+    let client_builder = || {
+        pooled(cookie_jar.client_builder())
+            .connect_timeout(connect_timeout)
+            .user_agent(user_agent)
+    };
+    let build_client = |client: Result<reqwest::Client, String>| {
+        client.unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(exit_code::USAGE_ERROR);
+        })
+    };
+    let api_transport = |builder| {
+        build_client(transport(
+            builder,
+            matches
+                .value_of("api_proxy")
+                .or_else(|| matches.value_of("proxy")),
+            matches.value_of("api_local_address"),
+        ))
+    };
+    let client = build_client(transport(
+        client_builder(),
+        matches
+            .value_of("media_proxy")
+            .or_else(|| matches.value_of("proxy")),
+        matches.value_of("media_local_address"),
+    ));
+
+    let state = state::State::open(&state_directory);
+    let started_at = chrono::Utc::now();
+    let started = std::time::Instant::now();
+    if matches.is_present("heap_stats") {
+        if !cfg!(feature = "heap-stats") {
+            eprintln!("--heap-stats needs a build with the heap-stats feature (cargo build --features heap-stats)");
+            std::process::exit(exit_code::USAGE_ERROR);
+        }
+        heap_stats::start();
+    }
+
     let output_urls = matches.value_of("output_urls");
 
-    println!("Using output directory {:?}", canonicalized_directory);
+    progress::status(format!("Starting run {}", run::id()));
+    tracing::info!("Starting run {}", run::id());
+    progress::status(format!(
+        "Using output directory {:?}",
+        canonicalized_directory
+    ));
 
     if let Some(filename) = output_urls {
-        println!("Storing retrieved urls in {}", filename);
+        progress::status(format!("Storing retrieved urls in {}", filename));
     }
 
+    let remote = match matches.value_of("output") {
+        Some(url) => {
+            progress::status(format!("Uploading images to {}", url));
+            let remote = remote::Remote::new(
+                url,
+                matches.value_of("s3_endpoint"),
+                matches.value_of("s3_region"),
+            )
+            .await;
+            Some(remote.unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(exit_code::IO_ERROR);
+            }))
+        }
+        None => None,
+    };
+
     let username = matches.value_of("username").unwrap();
     let max_image_count = matches
         .value_of("number_of_images")
         .unwrap()
         .parse::<u32>()
         .unwrap();
+    let max_tweet_count = matches
+        .value_of("max_tweets")
+        .map(|count| count.parse::<u32>().unwrap())
+        .unwrap_or(0);
 
-    let token = access_token(
-        matches.value_of("consumer_key").unwrap().to_string(),
-        matches.value_of("consumer_secret").unwrap().to_string(),
-        matches.value_of("access_token").unwrap().to_string(),
-        matches.value_of("access_token_secret").unwrap().to_string(),
-    );
-
-    let urls = get_urls(token, username.to_string(), max_image_count).await;
+    let timeline_options = TimelineOptions {
+        include_retweets: matches.is_present("include_retweets"),
+        include_replies: matches.is_present("include_replies"),
+        page_size: matches
+            .value_of("page_size")
+            .map(|size| size.parse().unwrap())
+            .unwrap_or(200),
+    };
+    let incremental = matches.is_present("incremental");
+    let filename_template = filename_template(&matches);
 
-    if let Some(filename) = output_urls {
-        let mut f = tokio::fs::File::create(filename)
-            .await
-            .expect(&format!("Could not open file for writing {}", filename));
-        for url in urls.iter() {
-            f.write_all(format!("{}\n", url).as_bytes())
-                .await
-                .expect(&format!("Could not write to file {}", filename));
+    let queue = queue::Queue::open(&state_directory, username);
+    let retry_failed = matches.value_of("retry_failed").map(|path| {
+        failed::read(std::path::Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(exit_code::IO_ERROR);
+        })
+    });
+    // Failed downloads are retried with the images recorded when they failed, at the path listed
+    // in the report, without retrieving the timeline again.
+    let mut retry_paths = std::collections::HashMap::new();
+    let resumed = if matches.is_present("resume") {
+        let pending: Vec<Media> = queue.pending();
+        if pending.is_empty() {
+            eprintln!("There are no pending downloads of {} to resume", username);
+            std::process::exit(exit_code::USAGE_ERROR);
         }
-        drop(f);
+        progress::status(format!("Resuming {} pending downloads", pending.len()));
+        Some(pending)
+    } else if let Some(retry_failed) = &retry_failed {
+        let mut pending = vec![];
+        for failed_download in retry_failed {
+            match queue.failure::<Media>(&failed_download.url) {
+                Some((path, media)) => {
+                    let path = failed_download.path.clone().unwrap_or(path);
+                    retry_paths.insert(media.url.clone(), path);
+                    pending.push(media);
+                }
+                None => eprintln!(
+                    "Warning: {} is not recorded as failed download of {}, skipping it",
+                    failed_download.url, username
+                ),
+            }
+        }
+        Some(pending)
+    } else {
+        None
+    };
+
+    // Failed downloads may be older than the newest tweet of the last run.
+    let since_id = if incremental && retry_failed.is_none() {
+        state.newest_tweet_id(username)
+    } else {
+        None
+    };
+    let filter = tweet_filter(&matches, &config, username, since_id);
+    if let Some(since_id) = filter.since_id {
+        progress::status(format!("Only retrieving tweets newer than {}", since_id));
     }
 
+    let dump = matches
+        .value_of("dump_tweets")
+        .map(|directory| dump::TweetDump::create(std::path::Path::new(directory)));
+    // Images are downloaded as soon as they are found, therefore images found in multiple sources
+    // are attributed to the source retrieved first.
+    let sources = match (
+        matches.is_present("with_likes"),
+        source::Source::parse(matches.value_of("prefer_source").unwrap_or("timeline")).unwrap(),
+    ) {
+        (false, _) => vec![source::Source::Timeline],
+        (true, source::Source::Timeline) => {
+            vec![source::Source::Timeline, source::Source::Likes]
+        }
+        (true, source::Source::Likes) => vec![source::Source::Likes, source::Source::Timeline],
+    };
+    let multi_progress = progress::multi_progress();
+    let (sender, receiver) = futures::channel::mpsc::unbounded();
+    let (found, collector) = match resumed {
+        Some(pending) => {
+            queue.replace(&pending);
+            drop(sender);
+            (futures::stream::iter(pending).left_stream(), None)
+        }
+        None => {
+            queue.clear();
+            let spinner = progress::Spinner::new(&multi_progress);
+            let collector = collector::Collector::new(
+                max_image_count,
+                max_tweet_count,
+                output_urls,
+                dump,
+                spinner,
+                sender,
+            );
+            (receiver.right_stream(), Some(collector))
+        }
+    };
+    let limit_reached = collector
+        .as_ref()
+        .map(|collector| collector.limit_reached());
+    let retrieval = async {
+        let collector = match collector {
+            Some(collector) => collector,
+            None => return Ok(()),
+        };
+        let backend = match matches.value_of("via_nitter") {
+            Some(instance) => Backend::Nitter {
+                client: api_transport(client_builder()),
+                instance: instance.to_string(),
+            },
+            None if matches.is_present("cookies_from_browser") => Backend::Web {
+                client: api_transport(web::session(client_builder())),
+            },
+            None => {
+                let fixtures = match (
+                    matches.value_of("record_fixtures"),
+                    matches.value_of("replay_fixtures"),
+                ) {
+                    (Some(directory), _) => fixtures::Fixtures::Record(directory.into()),
+                    (_, Some(directory)) => fixtures::Fixtures::Replay(directory.into()),
+                    _ => fixtures::Fixtures::Disabled,
+                };
+                // Replaying fixtures does not talk to the API, therefore no credentials are needed.
+                let tokens = if fixtures.is_replay() {
+                    vec![access_token(
+                        String::new(),
+                        String::new(),
+                        String::new(),
+                        String::new(),
+                    )]
+                } else {
+                    let mut tokens = vec![token_from_matches(&matches, &config)];
+                    tokens.extend(config.tokens.iter().cloned().map(|credentials| {
+                        access_token(
+                            credentials.consumer_key,
+                            credentials.consumer_secret,
+                            credentials.access_token,
+                            credentials.access_token_secret,
+                        )
+                    }));
+                    auth::usable_tokens(tokens).await.unwrap_or_else(|reason| {
+                        eprintln!("{}", reason);
+                        std::process::exit(exit_code::AUTH_FAILURE);
+                    })
+                };
+                Backend::Api { tokens, fixtures }
+            }
+        };
+        MediaCollector {
+            backend,
+            cookie_jar: &cookie_jar,
+            username: username.to_string(),
+            options: timeline_options,
+            sources: sources.clone(),
+            filter: &filter,
+        }
+        .collect(collector)
+        .await
+    };
+
+    // Every found image is stored in the persistent queue right away, before it is passed on to
+    // the downloads.
+    let mut images: u64 = 0;
+    let mut newest_tweet_id = None;
+    let print_urls = matches.is_present("print_urls");
+    let (queued_sender, queued) = futures::channel::mpsc::unbounded();
+    let queueing = async {
+        let queued_sender = queued_sender;
+        let mut found = found;
+        while let Some(media) = found.next().await {
+            // Retweets and quotes are attributed to their older originals, which makes this a safe
+            // lower bound of the newest retrieved tweet. Liked tweets do not tell anything about
+            // the timeline.
+            if media.source == source::Source::Timeline {
+                newest_tweet_id = newest_tweet_id.max(Some(media.tweet_id));
+            }
+            if print_urls {
+                println!("{}", media.url);
+            }
+            queue.push(images as usize, &media);
+            queued_sender
+                .unbounded_send((images as usize, media))
+                .expect("Could not pass image on to the downloads");
+            images += 1;
+        }
+    };
+
     let max_requests = matches
         .value_of("max_requests")
         .unwrap()
         .parse::<u32>()
         .unwrap();
 
-    download_urls(
-        urls,
-        max_requests,
-        canonicalized_directory.to_str().unwrap().to_string(),
+    let download_bars = progress::DownloadBars::new(multi_progress, max_requests);
+    let downloading = Downloader::new(
+        &client,
+        &queue,
+        DownloadOptions {
+            max_requests,
+            target_directory: canonicalized_directory.to_str().unwrap().to_string(),
+            naming: {
+                let template = Box::new(naming::Template::new(&filename_template));
+                if retry_paths.is_empty() {
+                    template
+                } else {
+                    Box::new(naming::Recorded::new(retry_paths, template))
+                }
+            },
+            download_window: matches
+                .value_of("download_window")
+                .map(|window| schedule::DownloadWindow::parse(window).unwrap()),
+            // The gallery shows the tweet text and links taken from the metadata.
+            write_metadata: matches.is_present("write_metadata") || matches.is_present("gallery"),
+            save_text: matches.is_present("save_text"),
+            embed_metadata: matches.is_present("embed_metadata"),
+            tweet_mtime: !matches.is_present("download_mtime"),
+            on_conflict: if matches.is_present("redownload") {
+                conflict::Conflict::Overwrite
+            } else {
+                conflict::Conflict::parse(matches.value_of("on_conflict").unwrap_or("skip"))
+                    .unwrap()
+            },
+            verify_size: matches.is_present("verify_size"),
+            verify_checksum: matches.is_present("verify_checksum"),
+            etag_cache: if matches.is_present("etag_cache") {
+                Some(etag::Cache::open(&state_directory))
+            } else {
+                None
+            },
+            archive: matches.value_of("archive").map(|path| {
+                archive::Archive::create(path).unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    std::process::exit(exit_code::IO_ERROR);
+                })
+            }),
+            remote,
+            withheld_client: matches.value_of("withheld_proxy").map(|proxy| {
+                build_client(transport(
+                    client_builder(),
+                    Some(proxy),
+                    matches.value_of("media_local_address"),
+                ))
+            }),
+            requests_per_second: matches
+                .value_of("requests_per_second")
+                .map(|limit| limit.parse().unwrap()),
+            read_timeout: Some(std::time::Duration::from_secs(
+                matches.value_of("read_timeout").unwrap().parse().unwrap(),
+            )),
+            slow_item_budget: matches
+                .value_of("slow_item_budget")
+                .map(|seconds| std::time::Duration::from_secs(seconds.parse().unwrap())),
+            formats: filter.formats.clone(),
+            min_file_size: matches
+                .value_of("min_file_size")
+                .map(|size| parse_file_size(size).unwrap()),
+            max_file_size: matches
+                .value_of("max_file_size")
+                .map(|size| parse_file_size(size).unwrap()),
+            per_file_timeout: matches
+                .value_of("per_file_timeout")
+                .map(|seconds| std::time::Duration::from_secs(seconds.parse().unwrap())),
+            preview_directory: matches.value_of("preview_directory").map(|directory| {
+                std::fs::create_dir_all(directory).unwrap_or_else(|_| {
+                    panic!("Preview directory '{:?}' could not be created.", directory)
+                });
+                std::fs::canonicalize(directory)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            }),
+            csv_export: matches.value_of("export_csv").map(|path| {
+                csv_export::CsvExport::create(path).unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    std::process::exit(exit_code::IO_ERROR);
+                })
+            }),
+            ndjson_manifest: if matches.is_present("write_manifest") {
+                Some(ndjson::Manifest::open(&canonicalized_directory))
+            } else {
+                None
+            },
+            manifest: if matches.is_present("archive_db") {
+                Some(manifest::Manifest::open(&canonicalized_directory))
+            } else {
+                None
+            },
+        },
     )
-    .await;
+    .download(queued, &download_bars);
+    terminal::handle_interrupts();
+    let (retrieved, (), downloaded) = futures::join!(retrieval, queueing, downloading);
+    download_bars.join().await;
+    let (downloaded, downloads) = downloaded.unwrap_or_else(|err| {
+        cookie_jar.save();
+        eprintln!("{}", err);
+        std::process::exit(exit_code::IO_ERROR);
+    });
+    if let Err(err) = &retrieved {
+        let message = format!("Retrieving the tweets of user {} failed: {}", username, err);
+        eprintln!("{}", message);
+        events::emit(events::Event::Error {
+            url: None,
+            message: &message,
+        });
+    }
+    if let Some(retry_failed) = &retry_failed {
+        progress::status(format!(
+            "Retried {} of {} failed downloads",
+            images,
+            retry_failed.len()
+        ));
+    }
+    let totals = summary::Summary::new(username, images, downloads, started.elapsed());
+    tracing::info!("{}", totals);
+    progress::status(totals.to_string());
+    if matches.is_present("write_summary") {
+        totals.write(&canonicalized_directory);
+    }
+
+    cookie_jar.save();
+    // An interrupted or failed run, or one stopped by --max-images or --max-tweets, may leave
+    // older tweets behind, which must not be skipped next time.
+    if let (true, None, None, Some(newest_tweet_id), false, false, Ok(())) = (
+        incremental,
+        &retry_failed,
+        filter.max_id,
+        newest_tweet_id,
+        interrupt::requested(),
+        limit_reached
+            .as_ref()
+            .is_some_and(|limit_reached| limit_reached.get()),
+        &retrieved,
+    ) {
+        state.set_newest_tweet_id(username, newest_tweet_id);
+    }
 
-    println!("Everything done! Have fun.");
+    let summary = state::RunSummary {
+        started_at,
+        username: username.to_string(),
+        images,
+        downloaded,
+        failed: totals.downloads.failed,
+        run_id: Some(run::id().to_string()),
+    };
+    if let Some(previous) = state.previous_run(username) {
+        progress::status(summary.delta(&previous));
+    }
+    state.record_run(&summary);
+
+    if matches.is_present("gallery") {
+        gallery::update(&canonicalized_directory);
+    }
+    if matches.is_present("status_page") {
+        status::write_status_page(
+            &canonicalized_directory,
+            &state.latest_runs(),
+            &config.watch,
+        );
+    }
+
+    if interrupt::requested() {
+        progress::status(format!(
+            "Interrupted with {} images left, continue the run with --resume",
+            queue.len()
+        ));
+        if !queue.is_empty() {
+            std::process::exit(exit_code::INTERRUPTED);
+        }
+        return;
+    }
+    if let Err(err) = &retrieved {
+        std::process::exit(exit_code::of_retrieval(err));
+    }
+    if totals.downloads.failed > 0 {
+        std::process::exit(exit_code::PARTIAL_FAILURE);
+    }
+    progress::status("Everything done! Have fun.".to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_file_sizes_with_units() {
+        assert_eq!(parse_file_size("500"), Ok(500));
+        assert_eq!(parse_file_size("50K"), Ok(50 * 1024));
+        assert_eq!(parse_file_size("10m"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_file_size(" 1G "), Ok(1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn rejects_invalid_file_sizes() {
+        assert!(parse_file_size("").is_err());
+        assert!(parse_file_size("K").is_err());
+        assert!(parse_file_size("10T").is_err());
+        assert!(parse_file_size("-1").is_err());
+        assert!(parse_file_size("1.5M").is_err());
+    }
+
+    #[test]
+    fn rejects_file_sizes_which_overflow() {
+        assert!(parse_file_size("99999999999G").is_err());
+        assert!(parse_file_size("18446744073709551615K").is_err());
+        assert_eq!(
+            parse_file_size("18446744073709551615"),
+            Ok(18446744073709551615)
+        );
+    }
 }
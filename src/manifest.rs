@@ -0,0 +1,134 @@
+use crate::canonical;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Name of the manifest database inside the output directory.
+const MANIFEST_FILE: &str = "archive.db";
+
+/// SQLite database recording every downloaded image, which may be queried by other tools.
+pub struct Manifest {
+    connection: Mutex<Connection>,
+}
+
+/// A single downloaded image recorded in the manifest.
+#[derive(Debug)]
+pub struct Entry {
+    pub url: String,
+    pub path: String,
+    pub sha256: String,
+}
+
+/// Hex encoded SHA-256 hash of the given data.
+pub fn hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl Manifest {
+    /// Whether the given output directory contains a manifest.
+    pub fn exists(output_directory: &Path) -> bool {
+        output_directory.join(MANIFEST_FILE).is_file()
+    }
+
+    pub fn open(output_directory: &Path) -> Manifest {
+        let path = output_directory.join(MANIFEST_FILE);
+        let connection = Connection::open(&path)
+            .unwrap_or_else(|err| panic!("Could not open manifest {:?}: {}", path, err));
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS media (
+                    id INTEGER PRIMARY KEY,
+                    tweet_id INTEGER NOT NULL,
+                    media_key TEXT NOT NULL,
+                    url TEXT NOT NULL,
+                    path TEXT NOT NULL,
+                    sha256 TEXT NOT NULL,
+                    downloaded_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS media_media_key ON media (media_key);
+                CREATE INDEX IF NOT EXISTS media_sha256 ON media (sha256);
+                CREATE INDEX IF NOT EXISTS media_path ON media (path);",
+            )
+            .expect("Could not initialize manifest");
+        // Added after the first release, fails if the column exists already.
+        let _ = connection.execute("ALTER TABLE media ADD COLUMN run_id TEXT", []);
+
+        Manifest {
+            connection: Mutex::new(connection),
+        }
+    }
+
+    /// A random sample of the recorded images.
+    pub fn sample(&self, count: usize) -> Vec<Entry> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT url, path, sha256 FROM media ORDER BY RANDOM() LIMIT ?1")
+            .expect("Could not read manifest");
+        statement
+            .query_map(params![count as i64], |row| {
+                Ok(Entry {
+                    url: row.get(0)?,
+                    path: row.get(1)?,
+                    sha256: row.get(2)?,
+                })
+            })
+            .and_then(|rows| rows.collect())
+            .expect("Could not read manifest")
+    }
+
+    /// Path of an already stored image with the given content hash.
+    pub fn path_by_hash(&self, sha256: &str) -> Option<String> {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT path FROM media WHERE sha256 = ?1 LIMIT 1",
+                params![sha256],
+                |row| row.get(0),
+            )
+            .optional()
+            .expect("Could not read manifest")
+    }
+
+    /// Url of the image most recently stored at the given path.
+    pub fn url_by_path(&self, path: &str) -> Option<String> {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT url FROM media WHERE path = ?1 ORDER BY id DESC LIMIT 1",
+                params![path],
+                |row| row.get(0),
+            )
+            .optional()
+            .expect("Could not read manifest")
+    }
+
+    /// Record a downloaded image stored at the given path (relative to the output directory).
+    ///
+    /// The hash is calculated from the content as it was downloaded.
+    pub fn record(&self, tweet_id: u64, url: &str, path: &str, sha256: &str) {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO media (tweet_id, media_key, url, path, sha256, downloaded_at, run_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    tweet_id as i64,
+                    canonical::media_key(url),
+                    url,
+                    path,
+                    sha256,
+                    Utc::now().timestamp(),
+                    crate::run::id()
+                ],
+            )
+            .expect("Could not write manifest");
+    }
+}
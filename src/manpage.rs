@@ -0,0 +1,368 @@
+use crate::exit_code;
+use clap::{App, ArgSettings};
+use std::ffi::OsStr;
+use std::io::Write;
+
+/// Placeholders of `--filename-template`, see `naming::Template`.
+const TEMPLATE_PLACEHOLDERS: &[(&str, &str)] = &[
+    ("{filename}", "File name of the image as given by its url"),
+    ("{author}", "Screen name of the user, whose timeline is downloaded"),
+    (
+        "{original_author}",
+        "Screen name of the author of the tweet, which differs from {author} for retweets and quotes",
+    ),
+    ("{tweet_id}", "Id of the tweet carrying the image"),
+    ("{media_id}", "Id of the image itself"),
+    ("{year}", "Year the tweet was posted"),
+    ("{month}", "Month the tweet was posted, as two digits"),
+    ("{day}", "Day the tweet was posted, as two digits"),
+];
+
+/// Syntax of the values taken by the filter options.
+const FILTER_SYNTAX: &[(&str, &str)] = &[
+    (
+        "--media KINDS",
+        "photos, gifs, videos or all, combined with + (eg. photos+gifs).",
+    ),
+    (
+        "--newer-than, --older-than AGE",
+        "A number followed by h (hours), d (days), w (weeks), m (months of 30 days) or y (years of 365 days), eg. 30d.",
+    ),
+    (
+        "--since-date, --until-date DATE",
+        "A date as YYYY-MM-DD in UTC. Both ends of the range are inclusive.",
+    ),
+    (
+        "--since-id, --max-id ID",
+        "The numeric id of a tweet, as found at the end of its url.",
+    ),
+    (
+        "--match-text, --exclude-text REGEX",
+        "A regular expression in the syntax of the Rust regex crate, eg. (?i)commission|wip. Retweets are checked by the text of the retweeted tweet.",
+    ),
+    (
+        "--exclude-keywords KEYWORDS",
+        "Comma separated plain terms, compared ignoring case.",
+    ),
+    (
+        "--with-hashtag HASHTAG",
+        "A hashtag with or without the leading #, compared ignoring case. May be given multiple times to accept any of them.",
+    ),
+    (
+        "--formats FORMATS",
+        "Comma separated file formats (eg. jpg,png), compared with the extension of the url or the Content-Type of the response.",
+    ),
+    (
+        "--min-file-size, --max-file-size SIZE",
+        "A number of bytes, which may be followed by K, M or G (multiples of 1024), eg. 50K.",
+    ),
+];
+
+/// Exit codes of a run, see `exit_code`.
+const EXIT_CODES: &[(i32, &str)] = &[
+    (0, "Success."),
+    (
+        exit_code::AUTH_FAILURE,
+        "The credentials are missing or were rejected.",
+    ),
+    (
+        exit_code::USER_UNAVAILABLE,
+        "The user does not exist, is suspended or is protected.",
+    ),
+    (
+        exit_code::PARTIAL_FAILURE,
+        "Some images could not be downloaded, or the timeline could only be retrieved partially.",
+    ),
+    (
+        exit_code::IO_ERROR,
+        "A file or database could not be read or written.",
+    ),
+    (
+        exit_code::USAGE_ERROR,
+        "The arguments are invalid, or do not leave anything to do.",
+    ),
+    (
+        exit_code::INTERNAL_ERROR,
+        "Something failed unexpectedly, see the printed message.",
+    ),
+    (
+        exit_code::INTERRUPTED,
+        "The run has been interrupted with Ctrl-C and left images to continue with --resume.",
+    ),
+];
+
+/// Escape text, so it is not interpreted as roff escapes.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
+
+/// Protect a line of text, which would be interpreted as roff request otherwise.
+fn line(text: &str) -> String {
+    if text.starts_with('.') || text.starts_with('\'') {
+        format!("\\&{}", text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn bold(text: &str) -> String {
+    format!("\\fB{}\\fR", escape(text))
+}
+
+fn italic(text: &str) -> String {
+    format!("\\fI{}\\fR", escape(text))
+}
+
+/// A documented argument: the term it is used by and its description.
+struct Entry {
+    key: String,
+    term: String,
+    help: String,
+}
+
+/// Description of an argument, followed by its default and possible values.
+fn describe(
+    help: Option<&str>,
+    default: Option<&OsStr>,
+    possible_values: Option<&Vec<&str>>,
+) -> String {
+    let mut description = escape(help.unwrap_or_default());
+    if let Some(default) = default {
+        description.push_str(&format!(
+            " [default: {}]",
+            escape(&default.to_string_lossy())
+        ));
+    }
+    if let Some(possible_values) = possible_values {
+        description.push_str(&format!(
+            " [possible values: {}]",
+            escape(&possible_values.join(", "))
+        ));
+    }
+    description
+}
+
+/// The visible options of the given command, ordered like in `--help`.
+///
+/// clap 2 has no public accessors for the argument definitions, they are read from its parser
+/// like its own completion generator does.
+fn options(app: &App) -> Vec<Entry> {
+    let switch = |short: Option<char>, long: Option<&str>| {
+        let mut switch: Vec<String> = vec![];
+        if let Some(short) = short {
+            switch.push(bold(&format!("-{}", short)));
+        }
+        if let Some(long) = long {
+            switch.push(bold(&format!("--{}", long)));
+        }
+        switch.join(", ")
+    };
+    let key = |short: Option<char>, long: Option<&str>| {
+        long.map(str::to_string)
+            .or_else(|| short.map(|short| short.to_string()))
+            .unwrap_or_default()
+    };
+
+    let mut entries: Vec<Entry> = app
+        .p
+        .flags
+        .iter()
+        .filter(|flag| !flag.b.is_set(ArgSettings::Hidden))
+        .map(|flag| Entry {
+            key: key(flag.s.short, flag.s.long),
+            term: switch(flag.s.short, flag.s.long),
+            help: describe(flag.b.long_help.or(flag.b.help), None, None),
+        })
+        .chain(
+            app.p
+                .opts
+                .iter()
+                .filter(|opt| !opt.b.is_set(ArgSettings::Hidden))
+                .map(|opt| {
+                    let value_name = opt
+                        .v
+                        .val_names
+                        .as_ref()
+                        .and_then(|names| names.values().next().copied())
+                        .unwrap_or(opt.b.name);
+                    Entry {
+                        key: key(opt.s.short, opt.s.long),
+                        term: format!("{} {}", switch(opt.s.short, opt.s.long), italic(value_name)),
+                        help: describe(
+                            opt.b.long_help.or(opt.b.help),
+                            opt.v.default_val,
+                            opt.v.possible_vals.as_ref(),
+                        ),
+                    }
+                }),
+        )
+        .collect();
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    entries
+}
+
+/// The positional arguments of the given command, in their order.
+fn arguments(app: &App) -> Vec<Entry> {
+    app.p
+        .positionals
+        .values()
+        .map(|positional| {
+            let value_name = positional
+                .v
+                .val_names
+                .as_ref()
+                .and_then(|names| names.values().next().copied())
+                .unwrap_or(positional.b.name);
+            Entry {
+                key: positional.b.name.to_string(),
+                term: italic(value_name),
+                help: describe(
+                    positional.b.long_help.or(positional.b.help),
+                    positional.v.default_val,
+                    positional.v.possible_vals.as_ref(),
+                ),
+            }
+        })
+        .collect()
+}
+
+fn write_entries(out: &mut impl Write, entries: &[Entry]) -> std::io::Result<()> {
+    for entry in entries {
+        writeln!(out, ".TP")?;
+        writeln!(out, "{}", entry.term)?;
+        writeln!(out, "{}", line(&entry.help))?;
+    }
+    Ok(())
+}
+
+/// Document the given subcommand and all of its nested subcommands.
+fn write_subcommand(out: &mut impl Write, prefix: &str, app: &App) -> std::io::Result<()> {
+    let name = format!("{} {}", prefix, app.p.meta.name).trim().to_string();
+    writeln!(out, ".SS {}", escape(&name))?;
+    if let Some(about) = app.p.meta.long_about.or(app.p.meta.about) {
+        writeln!(out, "{}", line(&escape(about)))?;
+    }
+    let arguments = arguments(app);
+    let options = options(app);
+    if !arguments.is_empty() || !options.is_empty() {
+        writeln!(out, ".RS")?;
+        write_entries(out, &arguments)?;
+        write_entries(out, &options)?;
+        writeln!(out, ".RE")?;
+    }
+    for subcommand in &app.p.subcommands {
+        write_subcommand(out, &name, subcommand)?;
+    }
+    Ok(())
+}
+
+fn write_table(out: &mut impl Write, rows: &[(String, &str)]) -> std::io::Result<()> {
+    for (term, description) in rows {
+        writeln!(out, ".TP")?;
+        writeln!(out, "{}", term)?;
+        writeln!(out, "{}", line(&escape(description)))?;
+    }
+    Ok(())
+}
+
+/// Render the manual page of the given commandline interface as roff.
+pub fn write(app: &App, out: &mut impl Write) -> std::io::Result<()> {
+    let command = env!("CARGO_PKG_NAME");
+    let meta = &app.p.meta;
+
+    writeln!(
+        out,
+        ".TH {} 1 \"\" \"{} {}\" \"User Commands\"",
+        escape(&command.to_uppercase()),
+        escape(command),
+        escape(meta.version.unwrap_or(env!("CARGO_PKG_VERSION")))
+    )?;
+    writeln!(out, ".SH NAME")?;
+    writeln!(
+        out,
+        "{} \\- {}",
+        escape(command),
+        escape(meta.about.unwrap_or_default())
+    )?;
+
+    writeln!(out, ".SH SYNOPSIS")?;
+    writeln!(out, "{} [\\fIOPTIONS\\fR] \\fIUSERNAME\\fR", bold(command))?;
+    writeln!(out, ".br")?;
+    writeln!(
+        out,
+        "{} \\fISUBCOMMAND\\fR [\\fIOPTIONS\\fR]",
+        bold(command)
+    )?;
+
+    if let Some(about) = meta.long_about {
+        writeln!(out, ".SH DESCRIPTION")?;
+        writeln!(out, "{}", line(&escape(about)))?;
+    }
+
+    writeln!(out, ".SH ARGUMENTS")?;
+    write_entries(out, &arguments(app))?;
+    writeln!(out, ".SH OPTIONS")?;
+    write_entries(out, &options(app))?;
+
+    writeln!(out, ".SH SUBCOMMANDS")?;
+    for subcommand in &app.p.subcommands {
+        write_subcommand(out, "", subcommand)?;
+    }
+
+    writeln!(out, ".SH FILENAME TEMPLATE")?;
+    writeln!(
+        out,
+        "{} decides the path of every image relative to the output directory. It may contain directories and the following placeholders:",
+        bold("--filename-template")
+    )?;
+    write_table(
+        out,
+        &TEMPLATE_PLACEHOLDERS
+            .iter()
+            .map(|(placeholder, description)| (bold(placeholder), *description))
+            .collect::<Vec<_>>(),
+    )?;
+
+    writeln!(out, ".SH FILTERS")?;
+    writeln!(
+        out,
+        "Tweets and images are selected by the filter options, which take the following values. All given filters have to match."
+    )?;
+    write_table(
+        out,
+        &FILTER_SYNTAX
+            .iter()
+            .map(|(options, description)| (bold(options), *description))
+            .collect::<Vec<_>>(),
+    )?;
+
+    writeln!(out, ".SH EXIT STATUS")?;
+    write_table(
+        out,
+        &EXIT_CODES
+            .iter()
+            .map(|(code, description)| (bold(&code.to_string()), *description))
+            .collect::<Vec<_>>(),
+    )?;
+
+    writeln!(out, ".SH ENVIRONMENT")?;
+    write_table(
+        out,
+        &[
+            (
+                bold("RUST_LOG"),
+                "Filter of the log output, which takes precedence over -v and -vv.",
+            ),
+            (
+                bold("HTTPS_PROXY"),
+                "Proxy for all requests, unless --proxy is given.",
+            ),
+        ],
+    )?;
+
+    if let Some(author) = meta.author {
+        writeln!(out, ".SH AUTHOR")?;
+        writeln!(out, "{}", escape(author))?;
+    }
+    Ok(())
+}
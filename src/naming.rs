@@ -0,0 +1,76 @@
+use crate::{source, Media};
+use std::collections::HashMap;
+
+/// Decides about the path, relative to the output directory, a downloaded image is stored at.
+///
+/// Applications embedding the downloader can implement this to name files by arbitrary logic
+/// (eg. ids assigned by a database) instead of filename templates.
+pub trait NamingStrategy: Send + Sync {
+    /// Relative path for the given image, whose url ends in the given file name.
+    fn relative_path(&self, media: &Media, file_name: &str) -> String;
+}
+
+/// The default naming strategy, which renders a filename template.
+///
+/// Supported placeholders are `{filename}`, `{author}`, `{original_author}`, `{tweet_id}`,
+/// `{media_id}` and the creation date of the tweet as `{year}`, `{month}` and `{day}`.
+pub struct Template {
+    template: String,
+}
+
+impl Template {
+    pub fn new(template: &str) -> Template {
+        Template {
+            template: template.to_string(),
+        }
+    }
+}
+
+impl NamingStrategy for Template {
+    fn relative_path(&self, media: &Media, file_name: &str) -> String {
+        let date = |format: &str| match media.created_at {
+            Some(created_at) => created_at.format(format).to_string(),
+            None => "unknown".to_string(),
+        };
+        self.template
+            .replace("{filename}", file_name)
+            .replace("{author}", &media.author)
+            .replace("{original_author}", &media.original_author)
+            .replace("{tweet_id}", &media.tweet_id.to_string())
+            .replace(
+                "{media_id}",
+                &media.media_id.map(|id| id.to_string()).unwrap_or_default(),
+            )
+            .replace("{year}", &date("%Y"))
+            .replace("{month}", &date("%m"))
+            .replace("{day}", &date("%d"))
+    }
+}
+
+/// Stores images at the paths recorded for their url (eg. in a failure report), and all others
+/// according to another strategy.
+pub struct Recorded {
+    paths: HashMap<String, String>,
+    fallback: Box<dyn NamingStrategy>,
+}
+
+impl Recorded {
+    /// Use the given paths, relative to the output directory, keyed by the url of the image.
+    pub fn new(paths: HashMap<String, String>, fallback: Box<dyn NamingStrategy>) -> Recorded {
+        Recorded { paths, fallback }
+    }
+}
+
+impl NamingStrategy for Recorded {
+    fn relative_path(&self, media: &Media, file_name: &str) -> String {
+        match self.paths.get(&media.url) {
+            // The directory of liked images is added by the downloader.
+            Some(path) if media.source == source::Source::Likes => path
+                .strip_prefix(&format!("{}/", source::LIKES_DIRECTORY))
+                .unwrap_or(path)
+                .to_string(),
+            Some(path) => path.clone(),
+            None => self.fallback.relative_path(media, file_name),
+        }
+    }
+}
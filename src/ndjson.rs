@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Name of the manifest file inside the output directory.
+const MANIFEST_FILE: &str = "manifest.ndjson";
+
+/// Outcome of a single download.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Downloaded,
+    /// The target file already existed.
+    Skipped,
+    /// The same content had already been stored under a different path.
+    Duplicate,
+    /// The tweet is withheld and its media could not be retrieved.
+    Withheld,
+    /// The download failed, the reason is listed in `failed_urls.txt`.
+    Failed,
+    /// The file was sorted out by a filter, eg. because of its size.
+    Filtered,
+}
+
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    url: &'a str,
+    path: &'a str,
+    tweet_id: u64,
+    bytes: Option<u64>,
+    status: Status,
+    time: DateTime<Utc>,
+    run_id: &'static str,
+}
+
+/// Append-only log of every processed image with one JSON object per line, suitable for tools
+/// like `jq`.
+pub struct Manifest {
+    file: Mutex<File>,
+}
+
+impl Manifest {
+    pub fn open(output_directory: &Path) -> Manifest {
+        let path = output_directory.join(MANIFEST_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .unwrap_or_else(|err| panic!("Could not open manifest {:?}: {}", path, err));
+        Manifest {
+            file: Mutex::new(file),
+        }
+    }
+
+    /// Append the record of an image stored at the given path (relative to the output
+    /// directory).
+    pub fn append(&self, url: &str, path: &str, tweet_id: u64, bytes: Option<u64>, status: Status) {
+        let record = Record {
+            url,
+            path,
+            tweet_id,
+            bytes,
+            status,
+            time: Utc::now(),
+            run_id: crate::run::id(),
+        };
+        let mut line = serde_json::to_vec(&record).expect("Could not serialize manifest record");
+        line.push(b'\n');
+        // A single write keeps the lines intact, even if other processes append as well.
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(&line)
+            .expect("Could not write manifest");
+    }
+}
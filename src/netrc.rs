@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+/// A single machine entry of a `.netrc` file.
+#[derive(Debug, Default)]
+pub struct Entry {
+    pub login: Option<String>,
+    pub password: Option<String>,
+    pub account: Option<String>,
+}
+
+fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+    let home = dirs::home_dir()?;
+    [".netrc", "_netrc"]
+        .iter()
+        .map(|name| home.join(name))
+        .find(|path| path.exists())
+}
+
+/// The entry, which is currently being parsed.
+enum Section {
+    Other,
+    Machine,
+    Default,
+}
+
+/// Parse the contents of a `.netrc` file and return the entry for the given machine.
+///
+/// A `default` entry is used if no entry for the machine exists.
+fn parse(contents: &str, machine: &str) -> Option<Entry> {
+    let mut tokens = contents.split_whitespace();
+    let mut section = Section::Other;
+    let mut entry: Option<Entry> = None;
+    let mut default: Option<Entry> = None;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => {
+                if entry.is_some() {
+                    break;
+                }
+                if tokens.next() == Some(machine) {
+                    section = Section::Machine;
+                    entry = Some(Entry::default());
+                } else {
+                    section = Section::Other;
+                }
+            }
+            "default" => {
+                if entry.is_some() {
+                    break;
+                }
+                section = Section::Default;
+                default = Some(Entry::default());
+            }
+            "login" | "password" | "account" => {
+                let value = tokens.next().map(str::to_string);
+                let target = match section {
+                    Section::Machine => entry.as_mut(),
+                    Section::Default => default.as_mut(),
+                    Section::Other => None,
+                };
+                if let Some(target) = target {
+                    match token {
+                        "login" => target.login = value,
+                        "password" => target.password = value,
+                        _ => target.account = value,
+                    }
+                }
+            }
+            // Macro definitions are not supported and end the parsable section.
+            "macdef" => break,
+            _ => {}
+        }
+    }
+
+    entry.or(default)
+}
+
+/// Look up the `.netrc` entry for the given machine.
+pub fn lookup(machine: &str) -> Option<Entry> {
+    let contents = std::fs::read_to_string(netrc_path()?).ok()?;
+    parse(&contents, machine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_entry_of_machine() {
+        let contents = "machine example.com login other password secret\n\
+                        machine api.twitter.com\n  login key\n  password token\n  account secret\n";
+        let entry = parse(contents, "api.twitter.com").unwrap();
+        assert_eq!(entry.login.as_deref(), Some("key"));
+        assert_eq!(entry.password.as_deref(), Some("token"));
+        assert_eq!(entry.account.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn falls_back_to_default_entry() {
+        let contents = "machine example.com login other\ndefault login anonymous password guest";
+        let entry = parse(contents, "api.twitter.com").unwrap();
+        assert_eq!(entry.login.as_deref(), Some("anonymous"));
+        assert_eq!(entry.password.as_deref(), Some("guest"));
+    }
+
+    #[test]
+    fn prefers_machine_entry_over_default() {
+        let contents = "default login anonymous\nmachine api.twitter.com login key";
+        let entry = parse(contents, "api.twitter.com").unwrap();
+        assert_eq!(entry.login.as_deref(), Some("key"));
+    }
+
+    #[test]
+    fn does_not_mix_following_entries_into_the_machine_entry() {
+        let contents = "machine api.twitter.com login key\nmachine example.com password other";
+        let entry = parse(contents, "api.twitter.com").unwrap();
+        assert_eq!(entry.login.as_deref(), Some("key"));
+        assert_eq!(entry.password, None);
+    }
+
+    #[test]
+    fn stops_at_macro_definitions() {
+        let contents = "macdef init\nmachine api.twitter.com login key\n";
+        assert!(parse(contents, "api.twitter.com").is_none());
+    }
+
+    #[test]
+    fn returns_none_without_matching_entry() {
+        assert!(parse("machine example.com login other", "api.twitter.com").is_none());
+        assert!(parse("", "api.twitter.com").is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::collector::Collector;
+use crate::cookies::CookieJar;
+use crate::filter::{TweetFilter, Verdict};
+use crate::retrieval;
+use crate::source::Source;
+use crate::{Media, TimelineOptions};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+use tracing::{debug, warn};
+use url::Url;
+
+/// Map a Nitter proxied image path (eg. `/pic/orig/media%2FABC.jpg`) back to the twitter CDN url.
+fn media_url(path: &str) -> Option<String> {
+    let file_name = path
+        .trim_start_matches("/pic/orig/")
+        .trim_start_matches("/pic/")
+        .replace("%2F", "/");
+    let file_name = file_name.strip_prefix("media/")?;
+    let file_name = file_name.split('?').next()?;
+    Some(format!("http://pbs.twimg.com/media/{}", file_name))
+}
+
+/// Whether the given name is a valid twitter screen name (up to 15 letters, digits and
+/// underscores).
+fn is_screen_name(name: &str) -> bool {
+    (1..=15).contains(&name.len())
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+/// Parse the date of a tweet as displayed by Nitter (eg. `Oct 10, 2018 · 8:19 PM UTC`).
+fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(date, "%b %d, %Y · %I:%M %p UTC")
+        .ok()
+        .map(|date| DateTime::from_utc(date, Utc))
+}
+
+/// Retrieve the image urls of a user by scraping the media tab of a Nitter instance.
+///
+/// This is an alternative to the twitter API for cases, where no API access is available.
+pub async fn get_urls(
+    client: &reqwest::Client,
+    cookie_jar: &CookieJar,
+    instance: String,
+    username: String,
+    options: TimelineOptions,
+    filter: &TweetFilter,
+    mut collector: Collector,
+) -> Result<(), retrieval::Error> {
+    let mut result = Ok(());
+
+    let tweet_link = Regex::new(r#"class="tweet-link" href="/([^/"]+)/status/(\d+)"#).unwrap();
+    let tweet_date = Regex::new(r#"class="tweet-date"><a href="[^"]*" title="([^"]+)""#).unwrap();
+    let image = Regex::new(r#"class="still-image" href="([^"]+)""#).unwrap();
+    let show_more =
+        Regex::new(r#"<div class="show-more">\s*<a href="([^"]*cursor=[^"]+)""#).unwrap();
+
+    let spinner = collector.spinner();
+
+    let base_url = Url::parse(&instance)
+        .and_then(|url| url.join(&format!("{}/media", username)))
+        .unwrap_or_else(|_| panic!("Invalid Nitter instance url {}", instance));
+    let mut next_page = Some(base_url.clone());
+
+    'retrieval: while let Some(page_url) = next_page.take() {
+        if collector.is_full() {
+            break;
+        }
+        spinner.set_message(format!(
+            "Retrieving tweets for user {} via Nitter ({} tweets / {} images)...",
+            username,
+            collector.tweets(),
+            collector.len()
+        ));
+        debug!("Requesting {}", page_url);
+        let body = match cookie_jar.get(client, &page_url).await {
+            Ok(response) if response.status().is_success() => match response.text().await {
+                Ok(body) => body,
+                Err(err) => {
+                    result = Err(retrieval::Error::Request(format!(
+                        "Could not read {}: {}",
+                        page_url, err
+                    )));
+                    break;
+                }
+            },
+            Ok(response) => {
+                result = Err(retrieval::Error::Request(format!(
+                    "Nitter instance answered {} for {}",
+                    response.status(),
+                    page_url
+                )));
+                break;
+            }
+            Err(err) => {
+                result = Err(retrieval::Error::Request(format!(
+                    "Could not request {}: {}",
+                    page_url, err
+                )));
+                break;
+            }
+        };
+
+        for item in body.split(r#"<div class="timeline-item"#).skip(1) {
+            if collector.is_full() {
+                break 'retrieval;
+            }
+            let (original_author, tweet_id) = match tweet_link.captures(item) {
+                Some(captures) => (captures[1].to_string(), captures[2].parse().unwrap_or(0)),
+                None => continue,
+            };
+            collector.scanned();
+            // The author ends up in filenames, therefore anything but a valid screen name of an
+            // untrusted instance (eg. `..`) is dropped.
+            if !is_screen_name(&original_author) {
+                warn!(
+                    "Skipping tweet {} with invalid author {:?}",
+                    tweet_id, original_author
+                );
+                continue;
+            }
+
+            match filter.check_id(tweet_id) {
+                Verdict::Accept => {}
+                Verdict::Skip => continue,
+                // Pinned tweets are shown first regardless of their age.
+                Verdict::Stop if item.contains(r#"class="pinned""#) => {}
+                Verdict::Stop => break 'retrieval,
+            }
+
+            let created_at = tweet_date
+                .captures(item)
+                .and_then(|captures| parse_date(&captures[1]));
+            if let Some(created_at) = created_at {
+                match filter.check_date(created_at) {
+                    Verdict::Accept => {}
+                    Verdict::Skip => continue,
+                    Verdict::Stop => break 'retrieval,
+                }
+            }
+
+            if !options.include_retweets && !original_author.eq_ignore_ascii_case(&username) {
+                continue;
+            }
+            if !options.include_replies && item.contains(r#"class="replying-to""#) {
+                continue;
+            }
+            // Only photos can be extracted from the Nitter markup.
+            if !filter.media_kinds.photos {
+                continue;
+            }
+
+            if filter.check_photo_count(image.captures_iter(item).count()) != Verdict::Accept {
+                continue;
+            }
+            for captures in image.captures_iter(item) {
+                let url = match media_url(&captures[1]) {
+                    Some(url) => url,
+                    None => continue,
+                };
+                collector.add(Media {
+                    url,
+                    media_id: None,
+                    tweet_id,
+                    author: username.clone(),
+                    original_author: original_author.clone(),
+                    created_at,
+                    text: None,
+                    favorite_count: None,
+                    retweet_count: None,
+                    source: Source::Timeline,
+                    withheld: None,
+                    width: None,
+                    height: None,
+                });
+                if collector.is_full() {
+                    break 'retrieval;
+                }
+            }
+        }
+
+        next_page = show_more
+            .captures_iter(&body)
+            .last()
+            .and_then(|captures| base_url.join(&captures[1].replace("&amp;", "&")).ok());
+    }
+
+    spinner.finish_with_message(format!(
+        "Tweets for user {} retrieved via Nitter ({} tweets / {} images)...",
+        username,
+        collector.tweets(),
+        collector.len()
+    ));
+
+    collector.finish();
+    result
+}
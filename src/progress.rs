@@ -0,0 +1,354 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tracing::Instrument;
+
+/// Interval, in which status lines are printed if progress bars can not be drawn.
+const STATUS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Interval, in which the progress is checked for reached milestones in plain mode.
+const MILESTONE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How progress is displayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Animated spinners and progress bars.
+    Bars,
+    /// Periodic single line status output.
+    Lines,
+    /// Linear output for screen readers, reporting progress every given percent.
+    Plain(u64),
+    /// No progress display, but informational messages are still printed.
+    Off,
+    /// No progress output at all, warnings are printed to stderr.
+    Quiet,
+    /// Like `Quiet`, but every event is printed as a line of JSON to stdout.
+    Json,
+}
+
+static MODE: OnceLock<Mode> = OnceLock::new();
+
+/// The chosen progress display, which is `Quiet` unless chosen otherwise.
+pub fn mode() -> Mode {
+    *MODE.get().unwrap_or(&Mode::Quiet)
+}
+
+/// Choose how progress is displayed.
+///
+/// Needs to be called before any progress is displayed.
+pub fn set_mode(mode: Mode) {
+    let _ = MODE.set(mode);
+}
+
+/// Whether informational output is suppressed.
+pub fn is_quiet() -> bool {
+    matches!(mode(), Mode::Quiet | Mode::Json)
+}
+
+/// Print an informational message to stderr, unless output is suppressed.
+pub fn status(message: String) {
+    if !is_quiet() {
+        eprintln!("{}", message);
+    }
+}
+
+fn draw_target() -> ProgressDrawTarget {
+    match mode() {
+        Mode::Bars => ProgressDrawTarget::stderr(),
+        Mode::Lines | Mode::Plain(_) | Mode::Off | Mode::Quiet | Mode::Json => {
+            ProgressDrawTarget::hidden()
+        }
+    }
+}
+
+/// Spinner, which falls back to periodic status lines if it can not be drawn.
+#[derive(Clone)]
+pub struct Spinner {
+    bar: ProgressBar,
+    message: Arc<Mutex<Option<String>>>,
+    multi_progress: Arc<MultiProgress>,
+}
+
+impl Spinner {
+    /// Create a spinner, which is displayed together with the other bars of the given container.
+    pub fn new(multi_progress: &Arc<MultiProgress>) -> Spinner {
+        let bar = multi_progress.add(ProgressBar::new_spinner());
+        bar.enable_steady_tick(80);
+
+        let message: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+        if matches!(mode(), Mode::Lines | Mode::Plain(_)) {
+            let pending = Arc::downgrade(&message);
+            let printing = async move {
+                loop {
+                    tokio::time::sleep(STATUS_INTERVAL).await;
+                    let pending = match pending.upgrade() {
+                        Some(pending) => pending,
+                        None => break,
+                    };
+                    let message = pending.lock().unwrap().take();
+                    if let Some(message) = message {
+                        eprintln!("{}", message);
+                    }
+                }
+            };
+            tokio::spawn(printing.in_current_span());
+        }
+
+        Spinner {
+            bar,
+            message,
+            multi_progress: multi_progress.clone(),
+        }
+    }
+
+    pub fn set_message(&self, message: String) {
+        if matches!(mode(), Mode::Lines | Mode::Plain(_)) {
+            *self.message.lock().unwrap() = Some(message.clone());
+        }
+        self.bar.set_message(message);
+    }
+
+    pub fn println(&self, message: String) {
+        println(&self.bar, message);
+    }
+
+    pub fn enable_steady_tick(&self, ms: u64) {
+        self.bar.enable_steady_tick(ms);
+    }
+
+    pub fn disable_steady_tick(&self) {
+        self.bar.disable_steady_tick();
+    }
+
+    pub fn finish_with_message(&self, message: String) {
+        if matches!(mode(), Mode::Lines | Mode::Plain(_)) {
+            self.message.lock().unwrap().take();
+            eprintln!("{}", message);
+        }
+        self.bar.finish_with_message(message);
+    }
+
+    /// Stop drawing this and all other bars of its container, eg. while the user is asked for
+    /// input, until the returned guard is dropped.
+    pub fn suspend(&self) -> Suspended<'_> {
+        self.multi_progress
+            .set_draw_target(ProgressDrawTarget::hidden());
+        Suspended {
+            multi_progress: &self.multi_progress,
+        }
+    }
+}
+
+/// Guard of a suspended progress display, which is drawn again once the guard is dropped.
+pub struct Suspended<'a> {
+    multi_progress: &'a MultiProgress,
+}
+
+impl Drop for Suspended<'_> {
+    fn drop(&mut self) {
+        self.multi_progress.set_draw_target(draw_target());
+    }
+}
+
+/// Print a message above the given bar, or on its own line if the bar can not be drawn.
+pub fn println<S: AsRef<str>>(bar: &ProgressBar, message: S) {
+    match mode() {
+        Mode::Bars => bar.println(message),
+        Mode::Lines | Mode::Plain(_) | Mode::Off | Mode::Quiet | Mode::Json => {
+            eprintln!("{}", message.as_ref())
+        }
+    }
+}
+
+/// Create the container for the retrieval and download progress bars.
+pub fn multi_progress() -> Arc<MultiProgress> {
+    Arc::new(MultiProgress::with_draw_target(draw_target()))
+}
+
+/// Periodic status output of a progress bar, which can not be drawn.
+pub struct Reporter {
+    bar: ProgressBar,
+    label: &'static str,
+    task: Option<tokio::task::JoinHandle<()>>,
+    /// Milestone, which has been reported last in plain mode.
+    last_milestone: Arc<Mutex<Option<u64>>>,
+}
+
+fn print_status(bar: &ProgressBar, label: &str) {
+    eprintln!("{}: {}/{}", label, bar.position(), bar.length());
+}
+
+/// Print the last milestone reached by the given progress bar, unless it has been reported
+/// already.
+fn report_milestone(
+    bar: &ProgressBar,
+    label: &str,
+    step: u64,
+    last_milestone: &Mutex<Option<u64>>,
+) {
+    let milestone = match bar.length() {
+        0 => 100,
+        length if bar.position() >= length => 100,
+        length => bar.position() * 100 / length / step * step,
+    };
+    let mut last_milestone = last_milestone.lock().unwrap();
+    if *last_milestone != Some(milestone) {
+        *last_milestone = Some(milestone);
+        eprintln!(
+            "{}: {} percent ({} of {})",
+            label,
+            milestone,
+            bar.position(),
+            bar.length()
+        );
+    }
+}
+
+impl Reporter {
+    /// Start printing the state of the given progress bar periodically, if it can not be drawn.
+    pub fn start(bar: ProgressBar, label: &'static str) -> Reporter {
+        let last_milestone = Arc::new(Mutex::new(None));
+        let task = match mode() {
+            Mode::Bars | Mode::Off | Mode::Quiet | Mode::Json => None,
+            Mode::Lines => {
+                let bar = bar.clone();
+                Some(tokio::spawn(
+                    async move {
+                        let mut last_position = None;
+                        loop {
+                            if last_position != Some(bar.position()) {
+                                last_position = Some(bar.position());
+                                print_status(&bar, label);
+                            }
+                            tokio::time::sleep(STATUS_INTERVAL).await;
+                        }
+                    }
+                    .in_current_span(),
+                ))
+            }
+            Mode::Plain(step) => {
+                let bar = bar.clone();
+                let last_milestone = last_milestone.clone();
+                Some(tokio::spawn(
+                    async move {
+                        loop {
+                            report_milestone(&bar, label, step, &last_milestone);
+                            tokio::time::sleep(MILESTONE_INTERVAL).await;
+                        }
+                    }
+                    .in_current_span(),
+                ))
+            }
+        };
+
+        Reporter {
+            bar,
+            label,
+            task,
+            last_milestone,
+        }
+    }
+
+    /// Stop the periodic output and print the final state.
+    pub fn finish(self) {
+        if let Some(task) = self.task {
+            task.abort();
+            match mode() {
+                Mode::Plain(step) => {
+                    report_milestone(&self.bar, self.label, step, &self.last_milestone)
+                }
+                _ => print_status(&self.bar, self.label),
+            }
+        }
+    }
+}
+
+/// Receives the progress of the downloads of a `Downloader`.
+pub trait DownloadProgress: Send + Sync {
+    /// Another image is to be downloaded.
+    fn queued(&self);
+    /// An image has been processed, whatever the outcome.
+    fn processed(&self);
+    /// What the download of the image at the given queue position is currently doing.
+    fn status(&self, index: usize, message: String);
+    /// A message about the download of the image at the given queue position, which must stay
+    /// visible.
+    fn message(&self, index: usize, message: &str);
+    /// All downloads are done.
+    fn finish(&self);
+}
+
+/// Progress bar of the downloads, together with a spinner for every concurrent request.
+pub struct DownloadBars {
+    bar: ProgressBar,
+    spinners: Vec<ProgressBar>,
+    reporter: Mutex<Option<Reporter>>,
+    join_handle: tokio::task::JoinHandle<()>,
+}
+
+impl DownloadBars {
+    /// Add the bars to the given container, with one spinner per concurrent request.
+    pub fn new(multi_progress: Arc<MultiProgress>, max_requests: u32) -> DownloadBars {
+        // The number of images grows while the timeline is retrieved.
+        let bar = multi_progress.add(ProgressBar::new(0));
+        bar.set_prefix("Downloading Images");
+        let spinners = (0..max_requests.max(1))
+            .map(|_| {
+                let spinner = multi_progress.add(ProgressBar::new_spinner());
+                spinner.enable_steady_tick(80);
+                spinner
+            })
+            .collect();
+        let reporter = Reporter::start(bar.clone(), "Downloading Images");
+
+        // Ensure that the multiprogress is properly rendered.
+        let span = tracing::Span::current();
+        let join_handle = tokio::task::spawn_blocking(move || {
+            span.in_scope(|| multi_progress.join().unwrap());
+        });
+
+        DownloadBars {
+            bar,
+            spinners,
+            reporter: Mutex::new(Some(reporter)),
+            join_handle,
+        }
+    }
+
+    fn spinner(&self, index: usize) -> &ProgressBar {
+        &self.spinners[index % self.spinners.len()]
+    }
+
+    /// Wait until the finished bars are drawn for the last time.
+    pub async fn join(self) {
+        self.join_handle.await.unwrap();
+    }
+}
+
+impl DownloadProgress for DownloadBars {
+    fn queued(&self) {
+        self.bar.inc_length(1);
+    }
+
+    fn processed(&self) {
+        self.bar.inc(1);
+    }
+
+    fn status(&self, index: usize, message: String) {
+        self.spinner(index).set_message(message);
+    }
+
+    fn message(&self, index: usize, message: &str) {
+        println(self.spinner(index), message);
+    }
+
+    fn finish(&self) {
+        for spinner in self.spinners.iter() {
+            spinner.finish_and_clear();
+        }
+        self.bar.abandon();
+        if let Some(reporter) = self.reporter.lock().unwrap().take() {
+            reporter.finish();
+        }
+    }
+}
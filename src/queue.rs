@@ -0,0 +1,185 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Name of the queue database inside the state directory.
+const QUEUE_FILE: &str = "queue.db";
+
+/// Pending downloads of the current run, stored as SQLite database inside the state directory, so
+/// an interrupted run can be resumed without walking the timeline again.
+pub struct Queue {
+    connection: Mutex<Connection>,
+    username: String,
+}
+
+impl Queue {
+    pub fn open(state_directory: &Path, username: &str) -> Queue {
+        let path = state_directory.join(QUEUE_FILE);
+        let connection = Connection::open(&path)
+            .unwrap_or_else(|err| panic!("Could not open download queue {:?}: {}", path, err));
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS queue (
+                    username TEXT NOT NULL,
+                    position INTEGER NOT NULL,
+                    item TEXT NOT NULL,
+                    PRIMARY KEY (username, position)
+                );
+                CREATE TABLE IF NOT EXISTS failed (
+                    username TEXT NOT NULL,
+                    url TEXT NOT NULL,
+                    path TEXT NOT NULL,
+                    item TEXT NOT NULL,
+                    PRIMARY KEY (username, url)
+                );",
+            )
+            .expect("Could not initialize download queue");
+
+        Queue {
+            connection: Mutex::new(connection),
+            username: username.to_lowercase(),
+        }
+    }
+
+    /// The downloads left pending by an interrupted run, in their original order.
+    pub fn pending<T: DeserializeOwned>(&self) -> Vec<T> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT item FROM queue WHERE username = ?1 ORDER BY position")
+            .expect("Could not read download queue");
+        statement
+            .query_map(params![self.username], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+            .expect("Could not read download queue")
+            .iter()
+            .map(|item| serde_json::from_str(item).expect("Could not parse queued download"))
+            .collect()
+    }
+
+    /// Number of pending downloads.
+    pub fn len(&self) -> usize {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM queue WHERE username = ?1",
+                params![self.username],
+                |row| row.get::<_, i64>(0),
+            )
+            .expect("Could not read download queue") as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove the pending downloads of an earlier run.
+    pub fn clear(&self) {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "DELETE FROM queue WHERE username = ?1",
+                params![self.username],
+            )
+            .expect("Could not write download queue");
+    }
+
+    /// Replace the pending downloads with the given ones, which are identified by their position
+    /// from now on.
+    pub fn replace<T: Serialize>(&self, items: &[T]) {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection
+            .transaction()
+            .expect("Could not write download queue");
+        transaction
+            .execute(
+                "DELETE FROM queue WHERE username = ?1",
+                params![self.username],
+            )
+            .expect("Could not write download queue");
+        for (position, item) in items.iter().enumerate() {
+            let item = serde_json::to_string(item).expect("Could not serialize queued download");
+            transaction
+                .execute(
+                    "INSERT INTO queue (username, position, item) VALUES (?1, ?2, ?3)",
+                    params![self.username, position as i64, item],
+                )
+                .expect("Could not write download queue");
+        }
+        transaction
+            .commit()
+            .expect("Could not write download queue");
+    }
+
+    /// Add a download at the given position, as soon as it is found.
+    pub fn push<T: Serialize>(&self, position: usize, item: &T) {
+        let item = serde_json::to_string(item).expect("Could not serialize queued download");
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO queue (username, position, item) VALUES (?1, ?2, ?3)",
+                params![self.username, position as i64, item],
+            )
+            .expect("Could not write download queue");
+    }
+
+    /// Remember a download, which failed, together with the path it was to be stored at, so it can
+    /// be retried without retrieving the timeline again.
+    pub fn record_failure<T: Serialize>(&self, url: &str, path: &str, item: &T) {
+        let item = serde_json::to_string(item).expect("Could not serialize queued download");
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO failed (username, url, path, item) VALUES (?1, ?2, ?3, ?4)",
+                params![self.username, url, path, item],
+            )
+            .expect("Could not write download queue");
+    }
+
+    /// The failed download of the given url, together with the path it was to be stored at.
+    pub fn failure<T: DeserializeOwned>(&self, url: &str) -> Option<(String, T)> {
+        let (path, item) = self
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT path, item FROM failed WHERE username = ?1 AND url = ?2",
+                params![self.username, url],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()
+            .expect("Could not read download queue")?;
+        Some((
+            path,
+            serde_json::from_str(&item).expect("Could not parse queued download"),
+        ))
+    }
+
+    /// Forget the failed download of the given url, once it did not fail anymore.
+    pub fn forget_failure(&self, url: &str) {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "DELETE FROM failed WHERE username = ?1 AND url = ?2",
+                params![self.username, url],
+            )
+            .expect("Could not write download queue");
+    }
+
+    /// Remove the download at the given position, once it is done.
+    pub fn done(&self, position: usize) {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "DELETE FROM queue WHERE username = ?1 AND position = ?2",
+                params![self.username, position as i64],
+            )
+            .expect("Could not write download queue");
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{s3, sftp, webdav};
+
+/// Remote storage, which all downloads are uploaded to instead of the output directory.
+pub enum Remote {
+    S3(s3::Bucket),
+    WebDav(webdav::Server),
+    Sftp(sftp::Server),
+}
+
+impl Remote {
+    /// Select the storage backend by the scheme of the given url.
+    pub async fn new(
+        url: &str,
+        s3_endpoint: Option<&str>,
+        s3_region: Option<&str>,
+    ) -> Result<Remote, String> {
+        match url.split_once("://").map(|(scheme, _)| scheme) {
+            Some("s3") => Ok(Remote::S3(s3::Bucket::new(url, s3_endpoint, s3_region)?)),
+            Some("webdav") | Some("webdavs") => Ok(Remote::WebDav(webdav::Server::new(url)?)),
+            Some("sftp") => Ok(Remote::Sftp(sftp::Server::connect(url).await?)),
+            _ => Err(format!(
+                "Unsupported output {} (use s3://, webdav://, webdavs:// or sftp://)",
+                url
+            )),
+        }
+    }
+
+    /// Store a file under the given path (relative to the configured location).
+    pub async fn put(
+        &self,
+        client: &reqwest::Client,
+        path: &str,
+        data: &[u8],
+    ) -> Result<(), String> {
+        match self {
+            Remote::S3(bucket) => bucket.put(client, path, data).await,
+            Remote::WebDav(server) => server.put(client, path, data).await,
+            Remote::Sftp(server) => server.put(path, data).await,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use tracing::debug;
+
+/// Error code returned for accounts, which do not exist.
+const USER_NOT_FOUND: i32 = 50;
+/// Error code returned for suspended accounts.
+const USER_SUSPENDED: i32 = 63;
+
+/// Reason, why the retrieval of a timeline ended before it was complete.
+#[derive(Debug)]
+pub enum Error {
+    /// The credentials were rejected and could not be renewed.
+    Unauthorized,
+    /// The twitter API answered with an error.
+    Api(egg_mode::error::Error),
+    /// A page could not be requested or its response could not be read.
+    Request(String),
+    /// The account of the given screen name does not exist.
+    NotFound(String),
+    /// The account of the given screen name is suspended.
+    Suspended(String),
+    /// The account of the given screen name only shows its tweets to its followers.
+    Protected(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Unauthorized => {
+                write!(f, "The credentials were rejected and could not be renewed")
+            }
+            Error::Api(err) => write!(f, "{}", err),
+            Error::Request(reason) => write!(f, "{}", reason),
+            Error::NotFound(username) => write!(f, "Account @{} does not exist", username),
+            Error::Suspended(username) => write!(f, "Account @{} is suspended", username),
+            Error::Protected(username) => write!(
+                f,
+                "Account @{} is protected, authenticate as one of its followers to retrieve its images",
+                username
+            ),
+        }
+    }
+}
+
+/// Look up the account of the given user before paging its timeline, to report accounts, whose
+/// timeline can not be retrieved, instead of producing an empty result.
+///
+/// Only definite answers are reported as errors. Network failures, rate limits and expired
+/// credentials are left to the timeline requests.
+pub async fn check_account(username: &str, token: &egg_mode::Token) -> Result<(), Error> {
+    let user = match egg_mode::user::show(username.to_owned(), token).await {
+        Ok(user) => user.response,
+        Err(egg_mode::error::Error::TwitterError(_, errors)) => {
+            let codes: Vec<i32> = errors.errors.iter().map(|error| error.code).collect();
+            if codes.contains(&USER_SUSPENDED) {
+                return Err(Error::Suspended(username.to_string()));
+            }
+            if codes.contains(&USER_NOT_FOUND) {
+                return Err(Error::NotFound(username.to_string()));
+            }
+            debug!("Looking up user {} failed: {:?}", username, codes);
+            return Ok(());
+        }
+        Err(err) => {
+            debug!("Looking up user {} failed: {}", username, err);
+            return Ok(());
+        }
+    };
+    if !user.protected {
+        return Ok(());
+    }
+
+    // Application only credentials never see protected tweets.
+    if let egg_mode::Token::Bearer(_) = token {
+        return Err(Error::Protected(user.screen_name));
+    }
+    if let Ok(own) = egg_mode::auth::verify_tokens(token).await {
+        if own.id == user.id {
+            return Ok(());
+        }
+    }
+    match egg_mode::user::relation_lookup(vec![user.id], token).await {
+        Ok(relations) => {
+            let following = relations.iter().any(|relation| {
+                relation
+                    .connections
+                    .iter()
+                    .any(|connection| matches!(connection, egg_mode::user::Connection::Following))
+            });
+            if following {
+                Ok(())
+            } else {
+                Err(Error::Protected(user.screen_name))
+            }
+        }
+        Err(err) => {
+            debug!(
+                "Looking up the relation to user {} failed: {}",
+                username, err
+            );
+            Ok(())
+        }
+    }
+}
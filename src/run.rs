@@ -0,0 +1,17 @@
+use chrono::Utc;
+use std::sync::OnceLock;
+
+static RUN_ID: OnceLock<String> = OnceLock::new();
+
+/// Unique id of this invocation (eg. `20261015T091500Z-1f3a`), which is recorded in the state
+/// database, the manifests and the metadata sidecars, so every file can be traced back to the run
+/// that produced it.
+pub fn id() -> &'static str {
+    RUN_ID.get_or_init(|| {
+        format!(
+            "{}-{:04x}",
+            Utc::now().format("%Y%m%dT%H%M%SZ"),
+            std::process::id() & 0xffff
+        )
+    })
+}
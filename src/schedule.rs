@@ -0,0 +1,120 @@
+use crate::interrupt;
+use chrono::{Local, NaiveTime};
+use std::time::Duration;
+
+/// Time of day (in local time), during which downloads are allowed to happen.
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl DownloadWindow {
+    /// Parse a window like `01:00-06:00`. Windows may wrap around midnight (eg. `22:00-06:00`), but
+    /// must not be empty.
+    pub fn parse(window: &str) -> Result<DownloadWindow, String> {
+        let invalid = || format!("Invalid download window '{}' (use HH:MM-HH:MM)", window);
+        let (start, end) = window.split_once('-').ok_or_else(invalid)?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").map_err(|_| invalid())?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|_| invalid())?;
+        if start == end {
+            return Err(format!(
+                "Invalid download window '{}' (start and end must differ)",
+                window
+            ));
+        }
+        Ok(DownloadWindow { start, end })
+    }
+
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Time until the window opens, or `None` if it is currently open.
+    pub fn time_until_open(&self) -> Option<Duration> {
+        let now = Local::now().time();
+        if self.contains(now) {
+            return None;
+        }
+        let mut wait = self.start - now;
+        if wait < chrono::Duration::zero() {
+            wait = wait + chrono::Duration::days(1);
+        }
+        Some(wait.to_std().unwrap_or_default())
+    }
+
+    /// Wait until the window is open or the run is interrupted.
+    pub async fn wait(&self) {
+        while let Some(wait) = self.time_until_open() {
+            if interrupt::requested() {
+                break;
+            }
+            // Wake up every second to notice interruptions, which also copes with clock changes.
+            tokio::time::sleep(wait.min(Duration::from_secs(1))).await;
+        }
+    }
+}
+
+impl std::fmt::Display for DownloadWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn parses_windows() {
+        let window = DownloadWindow::parse("01:00-06:30").unwrap();
+        assert_eq!((window.start, window.end), (time(1, 0), time(6, 30)));
+        let window = DownloadWindow::parse(" 22:00 - 06:00 ").unwrap();
+        assert_eq!((window.start, window.end), (time(22, 0), time(6, 0)));
+    }
+
+    #[test]
+    fn rejects_empty_windows() {
+        assert!(DownloadWindow::parse("06:00-06:00").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_windows() {
+        assert!(DownloadWindow::parse("06:00").is_err());
+        assert!(DownloadWindow::parse("25:00-06:00").is_err());
+        assert!(DownloadWindow::parse("01:00-6pm").is_err());
+        assert!(DownloadWindow::parse("").is_err());
+    }
+
+    #[test]
+    fn contains_times_inside_the_window() {
+        let window = DownloadWindow::parse("01:00-06:00").unwrap();
+        assert!(window.contains(time(1, 0)));
+        assert!(window.contains(time(5, 59)));
+        assert!(!window.contains(time(6, 0)));
+        assert!(!window.contains(time(0, 59)));
+    }
+
+    #[test]
+    fn contains_times_of_windows_wrapping_around_midnight() {
+        let window = DownloadWindow::parse("22:00-06:00").unwrap();
+        assert!(window.contains(time(23, 0)));
+        assert!(window.contains(time(0, 0)));
+        assert!(window.contains(time(5, 59)));
+        assert!(!window.contains(time(6, 0)));
+        assert!(!window.contains(time(21, 59)));
+    }
+}
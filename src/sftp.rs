@@ -0,0 +1,192 @@
+use async_trait::async_trait;
+use russh::client;
+use russh::keys::{self, key};
+use russh_sftp::client::SftpSession;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use url::Url;
+
+/// Identity files tried for public key authentication, relative to `~/.ssh`.
+const IDENTITIES: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Accepts only servers, whose key is listed in `~/.ssh/known_hosts`.
+struct KnownHosts {
+    host: String,
+    port: u16,
+}
+
+#[async_trait]
+impl client::Handler for KnownHosts {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &key::PublicKey,
+    ) -> Result<bool, Self::Error> {
+        Ok(keys::check_known_hosts(
+            &self.host,
+            self.port,
+            server_public_key,
+        )?)
+    }
+}
+
+/// Directory on a server reachable via SFTP, given as `sftp://user@host/path`.
+pub struct Server {
+    directory: String,
+    sftp: SftpSession,
+    /// Directories known to exist on the server.
+    directories: Mutex<HashSet<String>>,
+    // Keeps the SSH connection open.
+    _session: client::Handle<KnownHosts>,
+}
+
+impl Server {
+    /// Connect to the server of the given `sftp://` url.
+    ///
+    /// A password given in the url is used for authentication, otherwise the default identity
+    /// files in `~/.ssh` are tried. The server needs to be listed in `~/.ssh/known_hosts`.
+    pub async fn connect(url: &str) -> Result<Server, String> {
+        let invalid = || format!("Invalid SFTP url {} (use sftp://user@host/path)", url);
+        let parsed = Url::parse(url).map_err(|_| invalid())?;
+        if parsed.scheme() != "sftp" {
+            return Err(invalid());
+        }
+        let host = parsed.host_str().ok_or_else(invalid)?.to_string();
+        let port = parsed.port().unwrap_or(22);
+        let username = match parsed.username() {
+            "" => std::env::var("USER").map_err(|_| invalid())?,
+            username => username.to_string(),
+        };
+        // `sftp://host/~/path` is relative to the home directory of the user.
+        let directory = match parsed.path().strip_prefix("/~") {
+            Some(path) => format!(".{}", path),
+            None => parsed.path().to_string(),
+        };
+
+        let handler = KnownHosts {
+            host: host.clone(),
+            port,
+        };
+        let mut session = client::connect(
+            Arc::new(client::Config::default()),
+            (host.as_str(), port),
+            handler,
+        )
+        .await
+        .map_err(|err| match err {
+            russh::Error::UnknownKey => format!(
+                "Host key of {} is not listed in ~/.ssh/known_hosts (connect with ssh once to add it)",
+                host
+            ),
+            err => format!("Could not connect to {}: {}", host, err),
+        })?;
+
+        let mut authenticated = false;
+        if let Some(password) = parsed.password() {
+            authenticated = session
+                .authenticate_password(username.as_str(), password)
+                .await
+                .unwrap_or(false);
+        } else if let Some(home) = dirs::home_dir() {
+            for identity in IDENTITIES.iter() {
+                let key = match keys::load_secret_key(home.join(".ssh").join(identity), None) {
+                    Ok(key) => key,
+                    Err(_) => continue,
+                };
+                if session
+                    .authenticate_publickey(username.as_str(), Arc::new(key))
+                    .await
+                    .unwrap_or(false)
+                {
+                    authenticated = true;
+                    break;
+                }
+            }
+        }
+        if !authenticated {
+            return Err(format!(
+                "Could not authenticate as {} at {}",
+                username, host
+            ));
+        }
+
+        let failed = |err: russh::Error| format!("Could not start SFTP on {}: {}", host, err);
+        let channel = session.channel_open_session().await.map_err(failed)?;
+        channel
+            .request_subsystem(true, "sftp")
+            .await
+            .map_err(failed)?;
+        let sftp = SftpSession::new(channel.into_stream())
+            .await
+            .map_err(|err| format!("Could not start SFTP on {}: {}", host, err))?;
+
+        Ok(Server {
+            directory: match directory.trim_end_matches('/') {
+                "" => ".".to_string(),
+                directory => directory.to_string(),
+            },
+            sftp,
+            directories: Mutex::new(HashSet::new()),
+            _session: session,
+        })
+    }
+
+    /// Create all missing parent directories of the given path.
+    async fn create_directories(&self, path: &str) -> Result<(), String> {
+        let segments: Vec<&str> = path.split('/').collect();
+        for depth in 1..segments.len() {
+            let directory = format!("{}/{}", self.directory, segments[..depth].join("/"));
+            if self.directories.lock().unwrap().contains(&directory) {
+                continue;
+            }
+            if !self
+                .sftp
+                .try_exists(directory.as_str())
+                .await
+                .unwrap_or(false)
+            {
+                self.sftp
+                    .create_dir(directory.as_str())
+                    .await
+                    .map_err(|err| {
+                        format!("Could not create remote directory {}: {}", directory, err)
+                    })?;
+            }
+            self.directories.lock().unwrap().insert(directory);
+        }
+        Ok(())
+    }
+
+    /// Store a file under the given path (relative to the target directory).
+    ///
+    /// The file is uploaded under a temporary name first and moved into place once it is
+    /// complete.
+    pub async fn put(&self, path: &str, data: &[u8]) -> Result<(), String> {
+        self.create_directories(path).await?;
+        let target = format!("{}/{}", self.directory, path);
+        let part = format!("{}.part", target);
+
+        let mut file = self
+            .sftp
+            .create(part.as_str())
+            .await
+            .map_err(|err| format!("Could not open remote file {}: {}", part, err))?;
+        let written = match file.write_all(data).await {
+            Ok(()) => file.shutdown().await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = written {
+            let _ = self.sftp.remove_file(part.as_str()).await;
+            return Err(format!("Could not write remote file {}: {}", part, err));
+        }
+
+        // Plain SFTP renames refuse to replace existing files.
+        let _ = self.sftp.remove_file(target.as_str()).await;
+        self.sftp
+            .rename(part.as_str(), target.as_str())
+            .await
+            .map_err(|err| format!("Could not move remote file to {}: {}", target, err))
+    }
+}
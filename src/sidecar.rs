@@ -0,0 +1,166 @@
+use crate::withheld::Withheld;
+use crate::Media;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Metadata stored next to every downloaded image.
+#[derive(Debug, Serialize)]
+struct Sidecar<'a> {
+    tweet_id: u64,
+    tweet_url: String,
+    author: &'a str,
+    original_author: &'a str,
+    text: Option<&'a str>,
+    created_at: Option<DateTime<Utc>>,
+    downloaded_at: DateTime<Utc>,
+    favorite_count: Option<i32>,
+    retweet_count: Option<i32>,
+    media_url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    withheld: Option<&'a Withheld>,
+    /// Id of the run, which downloaded the image for the first time.
+    first_seen_run: &'a str,
+    /// Tags attached by the `tag` subcommand.
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+}
+
+/// The parts of a stored sidecar, which are needed to analyze an archive.
+#[derive(Debug, Deserialize)]
+pub struct Metadata {
+    pub tweet_id: u64,
+    pub author: String,
+    pub original_author: String,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub media_url: Option<String>,
+    #[serde(default)]
+    pub first_seen_run: Option<String>,
+}
+
+/// Path of the metadata sidecar of the given image.
+pub fn path(image: &Path) -> PathBuf {
+    let mut path = image.as_os_str().to_owned();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+/// Serialized metadata of an image downloaded for the first time by this run.
+pub fn render(media: &Media) -> Vec<u8> {
+    render_with_run(media, crate::run::id(), &[])
+}
+
+fn render_with_run(media: &Media, first_seen_run: &str, tags: &[String]) -> Vec<u8> {
+    let sidecar = Sidecar {
+        tweet_id: media.tweet_id,
+        tweet_url: media.tweet_url(),
+        author: &media.author,
+        original_author: &media.original_author,
+        text: media.text.as_deref(),
+        created_at: media.created_at,
+        downloaded_at: Utc::now(),
+        favorite_count: media.favorite_count,
+        retweet_count: media.retweet_count,
+        media_url: &media.url,
+        withheld: media.withheld.as_ref(),
+        first_seen_run,
+        tags,
+    };
+    serde_json::to_vec_pretty(&sidecar).expect("Could not serialize metadata")
+}
+
+/// Write the metadata sidecar of a downloaded image.
+///
+/// The run, which first downloaded the image, and the tags of the image are kept from an existing
+/// sidecar.
+pub async fn write(image: &Path, media: &Media) -> Result<(), String> {
+    let path = path(image);
+    let part_path = part_path(&path);
+    let (first_seen_run, tags) = match read(&path) {
+        Some(metadata) => (metadata.first_seen_run, metadata.tags),
+        None => (None, vec![]),
+    };
+    let contents = render_with_run(
+        media,
+        first_seen_run
+            .as_deref()
+            .unwrap_or_else(|| crate::run::id()),
+        &tags,
+    );
+    crate::store(&part_path, &path.to_string_lossy(), &contents, None).await
+}
+
+/// Path of the caption file of the given image.
+pub fn text_path(image: &Path) -> PathBuf {
+    let mut path = image.as_os_str().to_owned();
+    path.push(".txt");
+    PathBuf::from(path)
+}
+
+/// Tweet text of a downloaded image as plain text, or `None` if the text is unknown.
+pub fn render_text(media: &Media) -> Option<Vec<u8>> {
+    // The API delivers the text with HTML entities escaped.
+    let text = media
+        .text
+        .as_deref()?
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    Some(format!("{}\n", text).into_bytes())
+}
+
+/// Write the tweet text of a downloaded image to its caption file.
+pub async fn write_text(image: &Path, media: &Media) -> Result<(), String> {
+    match render_text(media) {
+        Some(text) => {
+            let path = text_path(image);
+            let part_path = part_path(&path);
+            crate::store(&part_path, &path.to_string_lossy(), &text, None).await
+        }
+        None => Ok(()),
+    }
+}
+
+/// Temporary file, which a sidecar is written to before being moved into place.
+fn part_path(path: &Path) -> PathBuf {
+    let mut part_path = path.as_os_str().to_owned();
+    part_path.push(".part");
+    PathBuf::from(part_path)
+}
+
+/// Read a metadata sidecar, returning `None` if the file is not a valid sidecar.
+pub fn read(path: &Path) -> Option<Metadata> {
+    let contents = std::fs::read(path).ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+/// Store the given tags in the metadata sidecar of an image, keeping all other metadata.
+///
+/// A sidecar only holding the tags is created if the image has none yet.
+pub fn write_tags(image: &Path, tags: &[String]) -> Result<(), String> {
+    let path = path(image);
+    let mut sidecar = std::fs::read(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_slice::<serde_json::Value>(&contents).ok())
+        .filter(|sidecar| sidecar.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    sidecar["tags"] = serde_json::json!(tags);
+    let contents = serde_json::to_vec_pretty(&sidecar).expect("Could not serialize metadata");
+    let part_path = part_path(&path);
+    std::fs::write(&part_path, contents)
+        .and_then(|_| std::fs::rename(&part_path, &path))
+        .map_err(|err| format!("Could not write file {:?}: {}", path, err))
+}
+
+/// All metadata sidecars below the given directory.
+///
+/// Hidden directories (like the state and staging directories) are skipped.
+pub fn find(directory: &Path) -> Vec<PathBuf> {
+    crate::walk::files(directory, &["json"])
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// Where an image has been found.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// The tweets (and optionally retweets) of the user.
+    Timeline,
+    /// The tweets liked by the user.
+    Likes,
+}
+
+impl Source {
+    pub fn parse(source: &str) -> Result<Source, String> {
+        match source {
+            "timeline" => Ok(Source::Timeline),
+            "likes" => Ok(Source::Likes),
+            _ => Err(format!(
+                "Unknown source '{}' (use timeline or likes)",
+                source
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Source::Timeline => "timeline",
+            Source::Likes => "likes",
+        }
+    }
+}
+
+/// Directory below the output directory, which receives the images of liked tweets.
+pub const LIKES_DIRECTORY: &str = "likes";
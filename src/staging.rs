@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+/// Name of the directory inside the output directory, which holds the partial downloads.
+const STAGING_DIRECTORY: &str = ".partial";
+
+/// Name of the directory inside the staging area, which keeps interrupted downloads across runs.
+const RESUME_DIRECTORY: &str = "resume";
+
+/// Per-run directory, which holds all downloads until they are complete.
+///
+/// The staging directory lives inside the output directory, so completed files can be moved into
+/// place with a simple rename. Interrupted downloads are kept in a directory shared by all runs,
+/// so they can be resumed later on.
+pub struct Staging {
+    directory: PathBuf,
+    resume_directory: PathBuf,
+}
+
+/// Whether the process with the given id is still running.
+///
+/// Outside of Linux this can not be determined cheaply, in which case every other run is
+/// considered to have crashed.
+fn is_running(pid: &str) -> bool {
+    cfg!(target_os = "linux") && Path::new("/proc").join(pid).exists()
+}
+
+impl Staging {
+    /// Create the staging directory of this run, removing orphaned directories of previous
+    /// crashed runs.
+    pub fn create(output_directory: &Path) -> Staging {
+        let root = output_directory.join(STAGING_DIRECTORY);
+        let pid = std::process::id().to_string();
+
+        if let Ok(entries) = std::fs::read_dir(&root) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name != pid && name != RESUME_DIRECTORY && !is_running(&name) {
+                    let _ = std::fs::remove_dir_all(entry.path());
+                }
+            }
+        }
+
+        let directory = root.join(pid);
+        let resume_directory = root.join(RESUME_DIRECTORY);
+        for directory in [&directory, &resume_directory] {
+            std::fs::create_dir_all(directory)
+                .unwrap_or_else(|_| panic!("Could not create directory {:?}", directory));
+        }
+
+        Staging {
+            directory,
+            resume_directory,
+        }
+    }
+
+    /// Path of the partial file for the download with the given index.
+    pub fn part_path(&self, index: usize) -> PathBuf {
+        self.directory.join(format!("{}.part", index))
+    }
+
+    /// Path of the partial file, which keeps the data of an interrupted download of the given url.
+    pub fn resume_path(&self, url: &str) -> PathBuf {
+        self.resume_directory
+            .join(format!("{}.part", crate::manifest::hash(url.as_bytes())))
+    }
+
+    /// Remove the staging directory after all downloads have been moved into place.
+    pub fn finish(self) {
+        let _ = std::fs::remove_dir_all(&self.directory);
+        // Only succeeds if there are no interrupted downloads left.
+        let _ = std::fs::remove_dir(&self.resume_directory);
+        if let Some(root) = self.directory.parent() {
+            // Only succeeds if no other run is using the staging area.
+            let _ = std::fs::remove_dir(root);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+
+/// Persistent state of previous runs, stored as SQLite database inside the state directory.
+pub struct State {
+    connection: Connection,
+}
+
+/// Key figures of a single run for one user.
+#[derive(Debug)]
+pub struct RunSummary {
+    pub started_at: DateTime<Utc>,
+    pub username: String,
+    pub images: u64,
+    pub downloaded: u64,
+    pub failed: u64,
+    /// Id of the run, unknown for runs recorded by older versions.
+    pub run_id: Option<String>,
+}
+
+fn signed(value: i64) -> String {
+    if value >= 0 {
+        format!("+{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+impl RunSummary {
+    /// Describe the change of this run compared to a previous one.
+    pub fn delta(&self, previous: &RunSummary) -> String {
+        format!(
+            "Compared to the previous run on {}: {} images, {} downloaded, {} failed",
+            previous.started_at.format("%Y-%m-%d %H:%M"),
+            signed(self.images as i64 - previous.images as i64),
+            signed(self.downloaded as i64 - previous.downloaded as i64),
+            signed(self.failed as i64 - previous.failed as i64),
+        )
+    }
+}
+
+impl State {
+    pub fn open(state_directory: &Path) -> State {
+        let path = state_directory.join("state.db");
+        let connection = Connection::open(&path)
+            .unwrap_or_else(|err| panic!("Could not open state database {:?}: {}", path, err));
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS runs (
+                    id INTEGER PRIMARY KEY,
+                    started_at INTEGER NOT NULL,
+                    username TEXT NOT NULL,
+                    images INTEGER NOT NULL,
+                    downloaded INTEGER NOT NULL,
+                    failed INTEGER NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS users (
+                    username TEXT PRIMARY KEY,
+                    newest_tweet_id INTEGER NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS tags (
+                    path TEXT NOT NULL,
+                    tag TEXT NOT NULL,
+                    PRIMARY KEY (path, tag)
+                );",
+            )
+            .expect("Could not initialize state database");
+        // Added after the first release, fails if the column exists already.
+        let _ = connection.execute("ALTER TABLE runs ADD COLUMN run_id TEXT", []);
+
+        State { connection }
+    }
+
+    /// The summary of the most recent run for the given user.
+    pub fn previous_run(&self, username: &str) -> Option<RunSummary> {
+        self.connection
+            .query_row(
+                "SELECT started_at, username, images, downloaded, failed, run_id FROM runs
+                 WHERE username = ?1 ORDER BY id DESC LIMIT 1",
+                params![username],
+                |row| {
+                    Ok(RunSummary {
+                        started_at: Utc.timestamp(row.get(0)?, 0),
+                        username: row.get(1)?,
+                        images: row.get(2)?,
+                        downloaded: row.get(3)?,
+                        failed: row.get(4)?,
+                        run_id: row.get(5)?,
+                    })
+                },
+            )
+            .optional()
+            .expect("Could not read state database")
+    }
+
+    /// The summary of the most recent run of every user, ordered by username.
+    pub fn latest_runs(&self) -> Vec<RunSummary> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT started_at, username, images, downloaded, failed, run_id FROM runs
+                 WHERE id IN (SELECT MAX(id) FROM runs GROUP BY username)
+                 ORDER BY username",
+            )
+            .expect("Could not read state database");
+        statement
+            .query_map([], |row| {
+                Ok(RunSummary {
+                    started_at: Utc.timestamp(row.get(0)?, 0),
+                    username: row.get(1)?,
+                    images: row.get(2)?,
+                    downloaded: row.get(3)?,
+                    failed: row.get(4)?,
+                    run_id: row.get(5)?,
+                })
+            })
+            .and_then(|rows| rows.collect())
+            .expect("Could not read state database")
+    }
+
+    pub fn record_run(&self, summary: &RunSummary) {
+        self.connection
+            .execute(
+                "INSERT INTO runs (started_at, username, images, downloaded, failed, run_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    summary.started_at.timestamp(),
+                    summary.username,
+                    summary.images,
+                    summary.downloaded,
+                    summary.failed,
+                    summary.run_id
+                ],
+            )
+            .expect("Could not write state database");
+    }
+
+    /// Id of the newest tweet retrieved for the given user by an incremental run.
+    pub fn newest_tweet_id(&self, username: &str) -> Option<u64> {
+        self.connection
+            .query_row(
+                "SELECT newest_tweet_id FROM users WHERE username = ?1",
+                params![username.to_lowercase()],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .expect("Could not read state database")
+            .map(|id| id as u64)
+    }
+
+    pub fn set_newest_tweet_id(&self, username: &str, id: u64) {
+        self.connection
+            .execute(
+                "INSERT INTO users (username, newest_tweet_id) VALUES (?1, ?2)
+                 ON CONFLICT (username)
+                 DO UPDATE SET newest_tweet_id = max(newest_tweet_id, excluded.newest_tweet_id)",
+                params![username.to_lowercase(), id as i64],
+            )
+            .expect("Could not write state database");
+    }
+
+    /// Tags of the archived file with the given path (relative to the output directory).
+    pub fn tags(&self, path: &str) -> Vec<String> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT tag FROM tags WHERE path = ?1 ORDER BY tag")
+            .expect("Could not read state database");
+        statement
+            .query_map(params![path], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .expect("Could not read state database")
+    }
+
+    pub fn add_tag(&self, path: &str, tag: &str) {
+        self.connection
+            .execute(
+                "INSERT OR IGNORE INTO tags (path, tag) VALUES (?1, ?2)",
+                params![path, tag],
+            )
+            .expect("Could not write state database");
+    }
+
+    pub fn remove_tag(&self, path: &str, tag: &str) {
+        self.connection
+            .execute(
+                "DELETE FROM tags WHERE path = ?1 AND tag = ?2",
+                params![path, tag],
+            )
+            .expect("Could not write state database");
+    }
+}
@@ -0,0 +1,75 @@
+use crate::gallery::escape;
+use crate::state::RunSummary;
+use std::path::Path;
+
+/// Name of the generated status page inside the output directory.
+pub const STATUS_FILE: &str = "status.html";
+
+fn render_row(username: &str, run: Option<&RunSummary>) -> String {
+    match run {
+        Some(run) => format!(
+            "<tr class=\"{class}\"><td>@{username}</td><td>{synced}</td><td>{images}</td><td>{downloaded}</td><td>{failed}</td><td>{run_id}</td></tr>\n",
+            class = if run.failed > 0 { "failed" } else { "ok" },
+            username = escape(username),
+            synced = run.started_at.format("%Y-%m-%d %H:%M UTC"),
+            images = run.images,
+            downloaded = run.downloaded,
+            failed = run.failed,
+            run_id = escape(run.run_id.as_deref().unwrap_or_default()),
+        ),
+        None => format!(
+            "<tr class=\"pending\"><td>@{}</td><td>never</td><td></td><td></td><td></td><td></td></tr>\n",
+            escape(username)
+        ),
+    }
+}
+
+/// Write a static HTML page showing the last run of every archived and watched account.
+///
+/// Watched accounts, which have not been downloaded yet, are listed as well.
+pub fn write_status_page(output_directory: &Path, runs: &[RunSummary], watched: &[String]) {
+    let mut rows: Vec<String> = runs
+        .iter()
+        .map(|run| render_row(&run.username, Some(run)))
+        .collect();
+    rows.extend(
+        watched
+            .iter()
+            .filter(|username| !runs.iter().any(|run| &run.username == *username))
+            .map(|username| render_row(username, None)),
+    );
+
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Twitter Image Archive Status</title>
+<style>
+body {{ font-family: sans-serif; margin: 1em; }}
+table {{ border-collapse: collapse; }}
+th, td {{ padding: 0.3em 1em; text-align: left; border-bottom: 1px solid #ddd; }}
+tr.ok td:first-child {{ border-left: 4px solid #2e7d32; }}
+tr.failed td:first-child {{ border-left: 4px solid #c62828; }}
+tr.pending td:first-child {{ border-left: 4px solid #9e9e9e; }}
+</style>
+</head>
+<body>
+<h1>Twitter Image Archive Status</h1>
+<p>Generated {generated}</p>
+<table>
+<tr><th>Account</th><th>Last sync</th><th>Images found</th><th>Downloaded</th><th>Failed</th><th>Run</th></tr>
+{rows}</table>
+</body>
+</html>
+"#,
+        generated = chrono::Utc::now().format("%Y-%m-%d %H:%M UTC"),
+        rows = rows.concat()
+    );
+
+    let path = output_directory.join(STATUS_FILE);
+    let part_path = output_directory.join(format!("{}.part", STATUS_FILE));
+    std::fs::write(&part_path, page)
+        .and_then(|_| std::fs::rename(&part_path, &path))
+        .unwrap_or_else(|_| panic!("Could not write file {:?}", path));
+}
@@ -0,0 +1,130 @@
+use crate::ndjson::Status;
+use indicatif::{HumanBytes, HumanDuration};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Name of the summary file inside the output directory.
+const SUMMARY_FILE: &str = "summary.json";
+
+static TWEETS_SCANNED: AtomicU64 = AtomicU64::new(0);
+
+pub fn tweet_scanned() {
+    TWEETS_SCANNED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Number of processed images per outcome, together with the transferred bytes.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Downloads {
+    pub downloaded: u64,
+    pub skipped_existing: u64,
+    pub duplicates: u64,
+    pub withheld: u64,
+    pub filtered: u64,
+    pub failed: u64,
+    pub bytes: u64,
+}
+
+impl Downloads {
+    /// Number of images processed, whatever the outcome.
+    pub fn processed(&self) -> u64 {
+        self.downloaded
+            + self.skipped_existing
+            + self.duplicates
+            + self.withheld
+            + self.filtered
+            + self.failed
+    }
+
+    pub fn record(&mut self, status: Status, bytes: Option<u64>) {
+        match status {
+            Status::Downloaded => {
+                self.downloaded += 1;
+                self.bytes += bytes.unwrap_or(0);
+            }
+            Status::Skipped => self.skipped_existing += 1,
+            Status::Duplicate => self.duplicates += 1,
+            Status::Withheld => self.withheld += 1,
+            Status::Filtered => self.filtered += 1,
+            Status::Failed => self.failed += 1,
+        }
+    }
+}
+
+/// Totals of a complete run, printed at its end.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub run_id: String,
+    pub username: String,
+    pub tweets_scanned: u64,
+    pub images_found: u64,
+    #[serde(flatten)]
+    pub downloads: Downloads,
+    pub seconds: f64,
+    pub bytes_per_second: f64,
+}
+
+impl Summary {
+    pub fn new(
+        username: &str,
+        images_found: u64,
+        downloads: Downloads,
+        elapsed: Duration,
+    ) -> Summary {
+        let seconds = elapsed.as_secs_f64();
+        Summary {
+            run_id: crate::run::id().to_string(),
+            username: username.to_string(),
+            tweets_scanned: TWEETS_SCANNED.load(Ordering::Relaxed),
+            images_found,
+            bytes_per_second: if seconds > 0.0 {
+                downloads.bytes as f64 / seconds
+            } else {
+                0.0
+            },
+            downloads,
+            seconds,
+        }
+    }
+
+    /// Write the summary to `summary.json` inside the output directory.
+    pub fn write(&self, output_directory: &Path) {
+        let path = output_directory.join(SUMMARY_FILE);
+        let contents = serde_json::to_string_pretty(self).expect("Could not serialize summary");
+        std::fs::write(&path, contents + "\n")
+            .unwrap_or_else(|_| panic!("Could not write file {:?}", path));
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let downloads = &self.downloads;
+        writeln!(f, "Summary for user {}:", self.username)?;
+        writeln!(f, "  Tweets scanned:   {}", self.tweets_scanned)?;
+        writeln!(f, "  Images found:     {}", self.images_found)?;
+        writeln!(f, "  Downloaded:       {}", downloads.downloaded)?;
+        writeln!(f, "  Skipped existing: {}", downloads.skipped_existing)?;
+        if downloads.duplicates > 0 {
+            writeln!(f, "  Duplicates:       {}", downloads.duplicates)?;
+        }
+        if downloads.withheld > 0 {
+            writeln!(f, "  Withheld:         {}", downloads.withheld)?;
+        }
+        if downloads.filtered > 0 {
+            writeln!(f, "  Filtered:         {}", downloads.filtered)?;
+        }
+        writeln!(f, "  Failed:           {}", downloads.failed)?;
+        writeln!(f, "  Transferred:      {}", HumanBytes(downloads.bytes))?;
+        writeln!(
+            f,
+            "  Wall time:        {}",
+            HumanDuration(Duration::from_secs_f64(self.seconds))
+        )?;
+        write!(
+            f,
+            "  Throughput:       {}/s",
+            HumanBytes(self.bytes_per_second as u64)
+        )
+    }
+}
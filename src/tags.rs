@@ -0,0 +1,54 @@
+use crate::exit_code;
+use crate::state::State;
+use std::path::Path;
+use twitter_image_downloader::sidecar;
+
+/// Add and remove user defined tags (eg. license notes or usage rights) of archived images and
+/// show the resulting tags.
+///
+/// Tags are stored in the state database as well as in the metadata sidecar of each image, so
+/// they travel with the file.
+pub fn tag(
+    output_directory: &Path,
+    state_directory: &Path,
+    files: &[&str],
+    add: &[&str],
+    remove: &[&str],
+) {
+    let state = State::open(state_directory);
+    for file in files {
+        let image = match std::fs::canonicalize(file) {
+            Ok(image) if image.is_file() => image,
+            _ => {
+                eprintln!("File {} does not exist", file);
+                std::process::exit(exit_code::USAGE_ERROR);
+            }
+        };
+        let path = match image.strip_prefix(output_directory) {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(_) => {
+                eprintln!(
+                    "File {} is not part of the archive {:?}",
+                    file, output_directory
+                );
+                std::process::exit(exit_code::USAGE_ERROR);
+            }
+        };
+
+        for tag in add {
+            state.add_tag(&path, tag);
+        }
+        for tag in remove {
+            state.remove_tag(&path, tag);
+        }
+        let tags = state.tags(&path);
+        if !add.is_empty() || !remove.is_empty() {
+            if let Err(err) = sidecar::write_tags(&image, &tags) {
+                eprintln!("{}", err);
+                std::process::exit(exit_code::IO_ERROR);
+            }
+        }
+
+        println!("{}: {}", path, tags.join(", "));
+    }
+}
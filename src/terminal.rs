@@ -0,0 +1,96 @@
+use crate::exit_code;
+use clap::ArgMatches;
+use tracing::Instrument;
+use twitter_image_downloader::interrupt;
+use twitter_image_downloader::progress::Mode;
+
+/// Terminals narrower than this can not display the progress bars without wrapping.
+const MINIMUM_WIDTH: u16 = 60;
+
+/// Detect whether the terminal is able to render the animated progress display.
+///
+/// Dumb terminals, terminals embedded in editors, GNU screen, narrow terminals and non
+/// interactive outputs all produce corrupted output with cursor movements.
+fn detect() -> Mode {
+    let term = console::Term::stderr();
+    if !term.features().is_attended() {
+        return Mode::Lines;
+    }
+
+    let term_name = std::env::var("TERM").unwrap_or_default();
+    if term_name.is_empty()
+        || term_name == "dumb"
+        || term_name == "emacs"
+        || std::env::var_os("INSIDE_EMACS").is_some()
+        || (term_name.starts_with("screen") && std::env::var_os("TMUX").is_none())
+    {
+        return Mode::Lines;
+    }
+
+    match term.size_checked() {
+        Some((_rows, columns)) if columns >= MINIMUM_WIDTH => Mode::Bars,
+        _ => Mode::Lines,
+    }
+}
+
+/// The progress display chosen on the commandline.
+///
+/// `--quiet` and `--json-progress` suppress it completely. Otherwise `--progress never` hides it,
+/// `--progress always` draws the animated display on every terminal (periodic status lines if
+/// stderr is no terminal at all), and `--plain` reports percentage milestones. By default it is
+/// detected whether the animated display can be drawn.
+pub fn progress_mode(matches: &ArgMatches) -> Mode {
+    if matches.is_present("json_progress") {
+        return Mode::Json;
+    }
+    if matches.is_present("quiet") {
+        return Mode::Quiet;
+    }
+    match matches.value_of("progress") {
+        Some("never") => return Mode::Off,
+        Some("always") if console::Term::stderr().is_term() => return Mode::Bars,
+        Some("always") => return Mode::Lines,
+        _ => {}
+    }
+    if matches.is_present("plain") {
+        return Mode::Plain(
+            matches
+                .value_of("plain_interval")
+                .unwrap_or("10")
+                .parse()
+                .unwrap(),
+        );
+    }
+    detect()
+}
+
+/// Force colored output on or off (`always`, `never`), or leave it to the detection of the
+/// terminal (`auto`), which honors `NO_COLOR`, `CLICOLOR` and `CLICOLOR_FORCE`.
+///
+/// Needs to be called before anything is printed.
+pub fn set_colors(when: &str) {
+    let enabled = match when {
+        "always" => true,
+        "never" => false,
+        _ => return,
+    };
+    console::set_colors_enabled(enabled);
+    console::set_colors_enabled_stderr(enabled);
+}
+
+/// Handle Ctrl-C by letting the running downloads finish without starting new ones, so no state
+/// is lost. Pressing Ctrl-C a second time aborts immediately.
+pub fn handle_interrupts() {
+    let handling = async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if interrupt::request() {
+                eprintln!("Aborted, continue the run with --resume");
+                std::process::exit(exit_code::INTERRUPTED);
+            }
+            eprintln!(
+                "Interrupted, finishing the running downloads (press Ctrl-C again to abort)..."
+            );
+        }
+    };
+    tokio::spawn(handling.in_current_span());
+}
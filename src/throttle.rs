@@ -0,0 +1,30 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Spreads requests evenly over time, so that no more than the given number of requests per second
+/// are started, regardless of the number of concurrent downloads.
+pub struct Throttle {
+    interval: Duration,
+    /// Earliest time the next request may be started.
+    next_slot: Mutex<Instant>,
+}
+
+impl Throttle {
+    pub fn new(requests_per_second: f64) -> Throttle {
+        Throttle {
+            interval: Duration::from_secs_f64(1.0 / requests_per_second),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until the next request may be started.
+    pub async fn wait(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+}
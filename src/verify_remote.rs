@@ -0,0 +1,80 @@
+use crate::exit_code;
+use std::path::Path;
+use twitter_image_downloader::manifest::{self, Manifest};
+
+/// Result of comparing an archived image with its source.
+enum Check {
+    Match,
+    Drift,
+    MissingLocally,
+    Unreachable(String),
+}
+
+async fn check(
+    client: &reqwest::Client,
+    output_directory: &Path,
+    entry: &manifest::Entry,
+) -> Check {
+    if !output_directory.join(&entry.path).is_file() {
+        return Check::MissingLocally;
+    }
+    let response = match client.get(&entry.url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => return Check::Unreachable(response.status().to_string()),
+        Err(err) => return Check::Unreachable(err.to_string()),
+    };
+    match response.bytes().await {
+        Ok(bytes) if manifest::hash(&bytes) == entry.sha256 => Check::Match,
+        Ok(_) => Check::Drift,
+        Err(err) => Check::Unreachable(err.to_string()),
+    }
+}
+
+/// Download a random sample of the archived images again and compare them with the hashes
+/// recorded at download time, reporting every image, which no longer matches its source.
+///
+/// Exits the process with a nonzero status code if any drift was found.
+pub async fn verify_remote(client: &reqwest::Client, output_directory: &Path, sample: usize) {
+    if !Manifest::exists(output_directory) {
+        eprintln!(
+            "No archive database found in {:?}. Download the images using --archive-db first.",
+            output_directory
+        );
+        std::process::exit(exit_code::USAGE_ERROR);
+    }
+    let entries = Manifest::open(output_directory).sample(sample);
+
+    let mut matched = 0;
+    let mut drifted = 0;
+    let mut missing = 0;
+    let mut unreachable = 0;
+    for entry in &entries {
+        match check(client, output_directory, entry).await {
+            Check::Match => matched += 1,
+            Check::Drift => {
+                drifted += 1;
+                println!("Drift: {} differs from {}", entry.path, entry.url);
+            }
+            Check::MissingLocally => {
+                missing += 1;
+                println!("Missing: {} does not exist locally", entry.path);
+            }
+            Check::Unreachable(reason) => {
+                unreachable += 1;
+                println!("Unreachable: {} ({})", entry.url, reason);
+            }
+        }
+    }
+
+    println!(
+        "Verified {} images: {} matching, {} drifted, {} missing, {} unreachable",
+        entries.len(),
+        matched,
+        drifted,
+        missing,
+        unreachable
+    );
+    if drifted > 0 || missing > 0 {
+        std::process::exit(exit_code::PARTIAL_FAILURE);
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::{Path, PathBuf};
+
+/// Extensions of the media files stored in an archive.
+pub const MEDIA_EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "png", "gif", "webp", "mp4"];
+
+fn collect(directory: &Path, extensions: &[&str], found: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect(&path, extensions, found);
+        } else if path.extension().is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        }) {
+            found.push(path);
+        }
+    }
+}
+
+/// All files with one of the given extensions below the given directory.
+///
+/// Hidden directories (like the state and staging directories) are skipped.
+pub fn files(directory: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+    let mut found = vec![];
+    collect(directory, extensions, &mut found);
+    found
+}
+
+/// Path of the given file relative to the output directory.
+pub fn relative_path(output_directory: &Path, path: &Path) -> String {
+    path.strip_prefix(output_directory)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
@@ -0,0 +1,140 @@
+use crate::collector::Collector;
+use crate::cookies::CookieJar;
+use crate::filter::{TweetFilter, Verdict};
+use crate::retrieval;
+use crate::{filtered_media, TimelineOptions};
+use tracing::debug;
+use url::Url;
+
+/// Bearer token of the twitter web client, which is accepted together with session cookies.
+const WEB_BEARER_TOKEN: &str = "AAAAAAAAAAAAAAAAAAAAANRILgAAAAAAnNwIzUejRCOuH5E6I8xnZz4puTs%3D1Zv7ttfk8LF81IUq16cHjhLTvJu4FA33AGWWjCpTnA";
+
+const USER_TIMELINE_URL: &str = "https://api.twitter.com/1.1/statuses/user_timeline.json";
+
+/// Let the client of the given builder authenticate using the browser session stored in its cookie
+/// jar.
+pub fn session(builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert(
+        reqwest::header::AUTHORIZATION,
+        format!("Bearer {}", WEB_BEARER_TOKEN).parse().unwrap(),
+    );
+    headers.insert("x-twitter-auth-type", "OAuth2Session".parse().unwrap());
+    builder.default_headers(headers)
+}
+
+/// Retrieve the image urls of a user, authenticating with the session cookies of a browser
+/// instead of developer API credentials.
+pub async fn get_urls(
+    client: &reqwest::Client,
+    cookie_jar: &CookieJar,
+    username: String,
+    options: TimelineOptions,
+    filter: &TweetFilter,
+    mut collector: Collector,
+) -> Result<(), retrieval::Error> {
+    let mut result = Ok(());
+    let mut max_id = filter.max_id;
+
+    let spinner = collector.spinner();
+
+    'retrieval: loop {
+        if collector.is_full() {
+            break;
+        }
+        spinner.set_message(format!(
+            "Retrieving tweets for user {} using browser session ({} tweets / {} images)...",
+            username,
+            collector.tweets(),
+            collector.len()
+        ));
+
+        let mut page_url = Url::parse(USER_TIMELINE_URL).unwrap();
+        page_url
+            .query_pairs_mut()
+            .append_pair("screen_name", &username)
+            .append_pair("count", &options.page_size.to_string())
+            .append_pair("tweet_mode", "extended")
+            .append_pair("exclude_replies", &(!options.include_replies).to_string())
+            .append_pair("include_rts", &options.include_retweets.to_string());
+        if let Some(since_id) = filter.since_id {
+            page_url
+                .query_pairs_mut()
+                .append_pair("since_id", &since_id.to_string());
+        }
+        if let Some(max_id) = max_id {
+            page_url
+                .query_pairs_mut()
+                .append_pair("max_id", &max_id.to_string());
+        }
+
+        debug!("Requesting {}", page_url);
+        let feed: Vec<egg_mode::tweet::Tweet> = match cookie_jar.get(client, &page_url).await {
+            Ok(response) if response.status().is_success() => match response.json().await {
+                Ok(feed) => feed,
+                Err(err) => {
+                    result = Err(retrieval::Error::Request(format!(
+                        "Could not parse timeline page: {}",
+                        err
+                    )));
+                    break;
+                }
+            },
+            Ok(response) => {
+                result = Err(retrieval::Error::Request(format!(
+                    "Browser session was rejected ({}), it may have expired",
+                    response.status()
+                )));
+                break;
+            }
+            Err(err) => {
+                result = Err(retrieval::Error::Request(format!(
+                    "Could not request timeline page: {}",
+                    err
+                )));
+                break;
+            }
+        };
+
+        for tweet in &feed {
+            if collector.is_full() {
+                break 'retrieval;
+            }
+            match filter.check(tweet) {
+                Verdict::Accept => {}
+                Verdict::Skip => {
+                    collector.scanned();
+                    continue;
+                }
+                Verdict::Stop => break 'retrieval,
+            }
+            let media = filtered_media(tweet, &username, filter);
+            if !media.is_empty() {
+                collector.dump(tweet);
+            }
+            for media in media {
+                collector.add(media);
+                if collector.is_full() {
+                    break 'retrieval;
+                }
+            }
+            collector.scanned();
+        }
+
+        match feed.iter().map(|tweet| tweet.id).min() {
+            Some(min_id) => max_id = Some(min_id - 1),
+            // We are looping the tweet cycle
+            None => break,
+        }
+    }
+
+    spinner.finish_with_message(format!(
+        "Tweets for user {} retrieved using browser session ({} tweets / {} images)...",
+        username,
+        collector.tweets(),
+        collector.len()
+    ));
+
+    collector.finish();
+    result
+}
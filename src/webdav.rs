@@ -0,0 +1,291 @@
+use crate::netrc;
+use md5::{Digest, Md5};
+use reqwest::{header, Method, StatusCode};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use url::Url;
+
+/// Digest challenge of the server, which is reused for all following requests.
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    qop: bool,
+    count: u32,
+}
+
+/// Collection on a WebDAV server (eg. Nextcloud), given as `webdav://server/path` or
+/// `webdavs://server/path` for HTTPS.
+pub struct Server {
+    base: Url,
+    username: Option<String>,
+    password: Option<String>,
+    challenge: Mutex<Option<Challenge>>,
+    /// Collections known to exist on the server.
+    collections: Mutex<HashSet<String>>,
+}
+
+fn md5(value: &str) -> String {
+    Md5::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Percent encode a single path segment.
+fn encode_segment(segment: &str) -> String {
+    url::form_urlencoded::byte_serialize(segment.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+/// Parse the parameters of a `WWW-Authenticate: Digest ...` header.
+fn parse_challenge(value: &str) -> Option<Challenge> {
+    let parameters = value.strip_prefix("Digest ")?;
+    let mut values = HashMap::new();
+    let mut quoted = false;
+    let parameters = parameters.split(|character| {
+        if character == '"' {
+            quoted = !quoted;
+        }
+        character == ',' && !quoted
+    });
+    for parameter in parameters {
+        if let Some((key, value)) = parameter.trim().split_once('=') {
+            values.insert(key.to_lowercase(), value.trim_matches('"').to_string());
+        }
+    }
+    Some(Challenge {
+        realm: values.remove("realm")?,
+        nonce: values.remove("nonce")?,
+        opaque: values.remove("opaque"),
+        qop: values
+            .get("qop")
+            .is_some_and(|qop| qop.split(',').any(|qop| qop.trim() == "auth")),
+        count: 0,
+    })
+}
+
+impl Server {
+    /// Parse a `webdav://` url. Credentials are taken from the url or the `.netrc` entry of the
+    /// server.
+    pub fn new(url: &str) -> Result<Server, String> {
+        let invalid = || format!("Invalid WebDAV url {} (use webdav://server/path)", url);
+        let (scheme, location) = if let Some(location) = url.strip_prefix("webdav://") {
+            ("http", location)
+        } else if let Some(location) = url.strip_prefix("webdavs://") {
+            ("https", location)
+        } else {
+            return Err(invalid());
+        };
+        let mut base = Url::parse(&format!("{}://{}", scheme, location)).map_err(|_| invalid())?;
+        let host = base.host_str().ok_or_else(invalid)?.to_string();
+
+        let netrc = netrc::lookup(&host).unwrap_or_default();
+        let username = Some(base.username().to_string())
+            .filter(|username| !username.is_empty())
+            .or(netrc.login);
+        let password = base.password().map(str::to_string).or(netrc.password);
+        let _ = base.set_username("");
+        let _ = base.set_password(None);
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+
+        Ok(Server {
+            base,
+            username,
+            password,
+            challenge: Mutex::new(None),
+            collections: Mutex::new(HashSet::new()),
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<Url, String> {
+        let path = path
+            .split('/')
+            .map(encode_segment)
+            .collect::<Vec<_>>()
+            .join("/");
+        self.base
+            .join(&path)
+            .map_err(|_| format!("Invalid WebDAV path {}", path))
+    }
+
+    /// Authorization header answering the current digest challenge.
+    fn digest_authorization(&self, method: &Method, url: &Url) -> Option<String> {
+        let mut challenge = self.challenge.lock().unwrap();
+        let challenge = challenge.as_mut()?;
+        let username = self.username.as_deref().unwrap_or_default();
+        let uri = url.path();
+        let ha1 = md5(&format!(
+            "{}:{}:{}",
+            username,
+            challenge.realm,
+            self.password.as_deref().unwrap_or_default()
+        ));
+        let ha2 = md5(&format!("{}:{}", method, uri));
+
+        let mut authorization = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\"",
+            username, challenge.realm, challenge.nonce, uri
+        );
+        if challenge.qop {
+            challenge.count += 1;
+            let cnonce = md5(&format!("{:?}", std::time::SystemTime::now()));
+            let response = md5(&format!(
+                "{}:{}:{:08x}:{}:auth:{}",
+                ha1, challenge.nonce, challenge.count, cnonce, ha2
+            ));
+            authorization.push_str(&format!(
+                ", qop=auth, nc={:08x}, cnonce=\"{}\", response=\"{}\"",
+                challenge.count, cnonce, response
+            ));
+        } else {
+            let response = md5(&format!("{}:{}:{}", ha1, challenge.nonce, ha2));
+            authorization.push_str(&format!(", response=\"{}\"", response));
+        }
+        if let Some(opaque) = &challenge.opaque {
+            authorization.push_str(&format!(", opaque=\"{}\"", opaque));
+        }
+        Some(authorization)
+    }
+
+    /// Send a request, answering basic or digest authentication challenges of the server.
+    async fn send(
+        &self,
+        client: &reqwest::Client,
+        method: Method,
+        url: &Url,
+        body: &[u8],
+    ) -> Result<reqwest::Response, String> {
+        let mut retried = false;
+        loop {
+            let mut request = client.request(method.clone(), url.clone());
+            if !body.is_empty() {
+                request = request.body(body.to_vec());
+            }
+            request = match self.digest_authorization(&method, url) {
+                Some(authorization) => request.header(header::AUTHORIZATION, authorization),
+                None if self.username.is_some() => {
+                    request.basic_auth(self.username.as_deref().unwrap(), self.password.as_ref())
+                }
+                None => request,
+            };
+            let response = request
+                .send()
+                .await
+                .map_err(|err| format!("Could not reach WebDAV server {}: {}", url, err))?;
+
+            if response.status() != StatusCode::UNAUTHORIZED || retried {
+                return Ok(response);
+            }
+            let challenge = response
+                .headers()
+                .get_all(header::WWW_AUTHENTICATE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .find_map(parse_challenge);
+            match challenge {
+                Some(challenge) if self.username.is_some() => {
+                    *self.challenge.lock().unwrap() = Some(challenge);
+                    retried = true;
+                }
+                _ => return Ok(response),
+            }
+        }
+    }
+
+    /// Create all missing parent collections of the given path.
+    async fn create_collections(&self, client: &reqwest::Client, path: &str) -> Result<(), String> {
+        let segments: Vec<&str> = path.split('/').collect();
+        for depth in 1..segments.len() {
+            let collection = segments[..depth].join("/");
+            if self.collections.lock().unwrap().contains(&collection) {
+                continue;
+            }
+            let url = self.url(&format!("{}/", collection))?;
+            let method = Method::from_bytes(b"MKCOL").unwrap();
+            let status = self.send(client, method, &url, &[]).await?.status();
+            // 405 signals an already existing collection.
+            if !status.is_success() && status != StatusCode::METHOD_NOT_ALLOWED {
+                return Err(format!(
+                    "Could not create WebDAV collection {}: {}",
+                    url, status
+                ));
+            }
+            self.collections.lock().unwrap().insert(collection);
+        }
+        Ok(())
+    }
+
+    /// Store a file under the given path (relative to the base collection).
+    pub async fn put(
+        &self,
+        client: &reqwest::Client,
+        path: &str,
+        data: &[u8],
+    ) -> Result<(), String> {
+        self.create_collections(client, path).await?;
+        let url = self.url(path)?;
+        let response = self.send(client, Method::PUT, &url, data).await?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Could not upload {} to WebDAV server: {}",
+                url,
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_digest_challenge() {
+        let challenge = parse_challenge(
+            r#"Digest realm="Nextcloud", nonce="dcd98b7102dd2f0e", opaque="5ccc069c403ebaf9", qop="auth""#,
+        )
+        .unwrap();
+        assert_eq!(challenge.realm, "Nextcloud");
+        assert_eq!(challenge.nonce, "dcd98b7102dd2f0e");
+        assert_eq!(challenge.opaque.as_deref(), Some("5ccc069c403ebaf9"));
+        assert!(challenge.qop);
+        assert_eq!(challenge.count, 0);
+    }
+
+    #[test]
+    fn parses_quoted_lists() {
+        let challenge =
+            parse_challenge(r#"Digest realm="Files, Photos", qop="auth-int,auth", nonce="abc""#)
+                .unwrap();
+        assert_eq!(challenge.realm, "Files, Photos");
+        assert_eq!(challenge.nonce, "abc");
+        assert!(challenge.qop);
+    }
+
+    #[test]
+    fn parses_challenge_without_qop() {
+        let challenge =
+            parse_challenge(r#"Digest realm="dav",nonce="abc",qop="auth-int""#).unwrap();
+        assert_eq!(challenge.opaque, None);
+        assert!(!challenge.qop);
+
+        assert!(
+            !parse_challenge(r#"Digest realm="dav", nonce="abc""#)
+                .unwrap()
+                .qop
+        );
+    }
+
+    #[test]
+    fn rejects_incomplete_challenges() {
+        assert!(parse_challenge(r#"Digest realm="dav""#).is_none());
+        assert!(parse_challenge(r#"Digest nonce="abc""#).is_none());
+        assert!(parse_challenge(r#"Basic realm="dav""#).is_none());
+        assert!(parse_challenge("").is_none());
+    }
+}
@@ -0,0 +1,60 @@
+use egg_mode::tweet::Tweet;
+use serde::{Deserialize, Serialize};
+
+/// Restriction of a tweet, whose content is not shown in some countries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Withheld {
+    /// Two-letter codes of the countries the tweet or its author is withheld in.
+    pub countries: Vec<String>,
+    /// Whether the tweet has been withheld due to a DMCA complaint.
+    pub copyright: bool,
+}
+
+impl std::fmt::Display for Withheld {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.copyright, self.countries.is_empty()) {
+            (true, _) => write!(f, "withheld due to a copyright complaint"),
+            (false, true) => write!(f, "withheld"),
+            (false, false) => write!(f, "withheld in {}", self.countries.join(", ")),
+        }
+    }
+}
+
+/// The restriction of the given tweet, either of the tweet itself or of its author.
+pub fn of(tweet: &Tweet) -> Option<Withheld> {
+    let mut countries = tweet.withheld_in_countries.clone().unwrap_or_default();
+    if let Some(user) = &tweet.user {
+        countries.extend(user.withheld_in_countries.iter().flatten().cloned());
+    }
+    countries.sort();
+    countries.dedup();
+    if countries.is_empty() && !tweet.withheld_copyright {
+        return None;
+    }
+    Some(Withheld {
+        countries,
+        copyright: tweet.withheld_copyright,
+    })
+}
+
+/// Download the media of a withheld tweet, or `None` if the CDN refuses to deliver it.
+///
+/// If the CDN refuses the direct request, the proxy client is tried, as withheld content is
+/// usually only blocked for the country the request seems to originate from.
+pub async fn fetch(
+    client: &reqwest::Client,
+    proxy_client: Option<&reqwest::Client>,
+    url: &str,
+    read_timeout: Option<std::time::Duration>,
+) -> Option<bytes::Bytes> {
+    for client in std::iter::once(client).chain(proxy_client) {
+        let response = match crate::try_read(url, read_timeout, client.get(url).send()).await {
+            Ok(response) if response.status().is_success() => response,
+            _ => continue,
+        };
+        if let Ok(bytes) = crate::try_read(url, read_timeout, response.bytes()).await {
+            return Some(bytes);
+        }
+    }
+    None
+}